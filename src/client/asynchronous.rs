@@ -0,0 +1,482 @@
+// --- std ---
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+// --- external ---
+use futures::{
+    Future, Stream,
+    future::{self, Loop},
+    stream,
+};
+use reqwest::{
+    Method, StatusCode,
+    r#async::{Client, Response},
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio_timer::Delay;
+// --- custom ---
+use super::{
+    Hook, Keys, TumblrClient, TumblrClientError, parse_body, response,
+    builder::TumblrClientBuilder,
+    blog::{
+        ContentBlock,
+        DeletePost,
+        GetBlogAvatar,
+        GetBlogAvatarOptionalParams,
+        GetBlogFollowers,
+        GetBlogFollowing,
+        GetBlogInfo,
+        GetBlogLikes,
+        GetBlogPosts,
+        GetBlogPostsDraft,
+        GetBlogPostsQueue,
+        GetBlogPostsSubmission,
+        GetBlogLikesOptionalParams,
+        GetBlogFollowingOptionalParams,
+        GetBlogFollowersOptionalParams,
+        GetBlogPostsOptionalParams,
+        GetBlogPostsQueueOptionalParams,
+        GetBlogPostsDraftOptionalParams,
+        GetBlogPostsSubmissionOptionalParams,
+        LegacyPostOptionalParams,
+        NpfContent,
+        NpfPostOptionalParams,
+        NpfReblog,
+        PostAction,
+        PostType,
+    },
+    endpoint::Endpoint,
+    model::{Blog, BlogInfo, Follower, Followers, Following, Likes, Post, PostId, Posts, User, UserInfo},
+    oauth::{RequestToken, parse_token, token_body},
+    oauth2::{self, OAuth2, is_bearer},
+    paginate::{self, Cursor, Pager},
+    rate_limit::{RateLimiter, retry_after},
+    raw::{Auth, RequestBody},
+    request::Request,
+    retry::{Attempt, RetryPolicy, classify},
+    user::{
+        FollowBlog,
+        GetUserDashboard,
+        GetUserDashboardOptionalParams,
+        GetUserFollowing,
+        GetUserFollowingOptionalParams,
+        GetUserInfo,
+        GetUserLikes,
+        GetUserLikesOptionalParams,
+        LikePost,
+        UnfollowBlog,
+        UnlikePost,
+    },
+};
+
+pub type TumblrFuture<T> = Box<dyn Future<Item = T, Error = TumblrClientError> + Send>;
+
+pub type TumblrStream<T> = Box<dyn Stream<Item = T, Error = TumblrClientError> + Send>;
+
+macro_rules! async_endpoints {
+    ($($name:ident($($arg:ident: $ty:ty),*) -> $resp:ty => $endpoint:expr;)*) => {
+        $(pub fn $name(&self, $($arg: $ty),*) -> TumblrFuture<$resp> { self.call($endpoint) })*
+    };
+}
+
+/// Endpoints whose response carries nothing worth returning.
+macro_rules! async_actions {
+    ($($name:ident($($arg:ident: $ty:ty),*) => $endpoint:expr;)*) => {
+        $(pub fn $name(&self, $($arg: $ty),*) -> TumblrFuture<()> { Box::new(self.call($endpoint).map(|_| ())) })*
+    };
+}
+
+/// Futures based counterpart of `TumblrClient`, driven by a tokio runtime.
+///
+/// Keys, retry and write policies and the throttle are taken from the wrapped `TumblrClient`.
+/// `WritePolicy::Verify` behaves like `WritePolicy::Unambiguous`.
+/// There is no interactive `oauth`, which would block on stdin: use `request_token` and `access_token`.
+#[derive(Debug, Clone)]
+pub struct AsyncTumblrClient {
+    pub session: Client,
+    client: TumblrClient,
+}
+
+impl Default for AsyncTumblrClient {
+    fn default() -> Self { AsyncTumblrClient::new() }
+}
+
+impl From<TumblrClient> for AsyncTumblrClient {
+    fn from(client: TumblrClient) -> Self {
+        AsyncTumblrClient {
+            session: Client::new(),
+            client,
+        }
+    }
+}
+
+impl TumblrClientBuilder {
+    pub fn build_async(self) -> Result<AsyncTumblrClient, TumblrClientError> {
+        Ok(AsyncTumblrClient {
+            session: self.async_session()?,
+            client: self.build()?,
+        })
+    }
+}
+
+impl AsyncTumblrClient {
+    pub fn new() -> AsyncTumblrClient { AsyncTumblrClient::from(TumblrClient::new()) }
+
+    /// See `TumblrClient::proxy`.
+    pub fn proxy(mut self, address: &str) -> Result<Self, TumblrClientError> {
+        self.session = TumblrClientBuilder::new().proxy(address).async_session()?;
+
+        Ok(self)
+    }
+
+    pub fn client(&self) -> &TumblrClient { &self.client }
+
+    pub fn into_client(self) -> TumblrClient { self.client }
+
+    pub fn request_token(&self, oauth_callback: Option<&str>) -> TumblrFuture<RequestToken> {
+        let sender = self.sender();
+        let client = self.client.clone();
+
+        Box::new(future::result(self.client.request_token_request(oauth_callback))
+            .and_then(move |request| sender.send(request))
+            .and_then(token_text)
+            .and_then(|body| parse_token(&body))
+            .map(move |(key, secret)| client.new_request_token(key, secret)))
+    }
+
+    pub fn access_token(self, request_token: &RequestToken, oauth_verifier: &str) -> TumblrFuture<Self> {
+        let request = match self.client.access_token_request(request_token, oauth_verifier) {
+            Ok(request) => request,
+            Err(e) => return Box::new(future::err(e)),
+        };
+
+        Box::new(self.sender()
+            .send(request)
+            .and_then(token_text)
+            .and_then(|body| parse_token(&body))
+            .map(move |(access_key, access_secret)| {
+                let mut client = self;
+                client.client.keys.access_key = access_key;
+                client.client.keys.access_secret = access_secret;
+
+                client
+            }))
+    }
+
+    /// See `TumblrClient::verify_credentials`.
+    pub fn verify_credentials(&self) -> TumblrFuture<User> { Box::new(self.get_user_info().map(|info| info.user)) }
+
+    async_endpoints! {
+        get_user_info() -> UserInfo => GetUserInfo;
+        get_user_dashboard(optional_params: Option<GetUserDashboardOptionalParams>) -> Posts => GetUserDashboard { optional_params };
+        get_user_likes(optional_params: Option<GetUserLikesOptionalParams>) -> Likes => GetUserLikes { optional_params };
+        get_user_following(optional_params: Option<GetUserFollowingOptionalParams>) -> Following => GetUserFollowing { optional_params };
+        get_blog_info(blog_identifier: &str) -> BlogInfo => GetBlogInfo { blog_identifier: blog_identifier.to_owned() };
+        get_blog_likes(blog_identifier: &str, optional_params: Option<GetBlogLikesOptionalParams>) -> Likes => GetBlogLikes { blog_identifier: blog_identifier.to_owned(), optional_params };
+        get_blog_following(blog_identifier: &str, optional_params: Option<GetBlogFollowingOptionalParams>) -> Following => GetBlogFollowing { blog_identifier: blog_identifier.to_owned(), optional_params };
+        get_blog_followers(blog_identifier: &str, optional_params: Option<GetBlogFollowersOptionalParams>) -> Followers => GetBlogFollowers { blog_identifier: blog_identifier.to_owned(), optional_params };
+        get_blog_posts(blog_identifier: &str, optional_params: Option<GetBlogPostsOptionalParams>) -> Posts => GetBlogPosts { blog_identifier: blog_identifier.to_owned(), optional_params };
+        get_blog_posts_queue(blog_identifier: &str, optional_params: Option<GetBlogPostsQueueOptionalParams>) -> Posts => GetBlogPostsQueue { blog_identifier: blog_identifier.to_owned(), optional_params };
+        get_blog_posts_draft(blog_identifier: &str, optional_params: Option<GetBlogPostsDraftOptionalParams>) -> Posts => GetBlogPostsDraft { blog_identifier: blog_identifier.to_owned(), optional_params };
+        get_blog_posts_submission(blog_identifier: &str, optional_params: Option<GetBlogPostsSubmissionOptionalParams>) -> Posts => GetBlogPostsSubmission { blog_identifier: blog_identifier.to_owned(), optional_params };
+        delete_post(blog_identifier: &str, id: &str) -> PostId => DeletePost { blog_identifier: blog_identifier.to_owned(), id: id.to_owned() };
+    }
+
+    async_actions! {
+        follow_blog(url: &str) => FollowBlog { url: url.to_owned() };
+        unfollow_blog(url: &str) => UnfollowBlog { url: url.to_owned() };
+        like_post(id: &str, reblog_key: &str) => LikePost { id: id.to_owned(), reblog_key: reblog_key.to_owned() };
+        unlike_post(id: &str, reblog_key: &str) => UnlikePost { id: id.to_owned(), reblog_key: reblog_key.to_owned() };
+    }
+
+    pub fn get_blog_avatar(&self, blog_identifier: &str, optional_params: Option<GetBlogAvatarOptionalParams>) -> TumblrFuture<Vec<u8>> {
+        Box::new(self.call_raw(GetBlogAvatar { blog_identifier: blog_identifier.to_owned(), optional_params })
+            .and_then(|resp| -> TumblrFuture<Vec<u8>> {
+                if resp.status().is_success() {
+                    Box::new(resp.into_body()
+                        .concat2()
+                        .map(|body| body.to_vec())
+                        .from_err())
+                } else { Box::new(json(resp).map(|_| vec![])) }
+            }))
+    }
+
+    pub fn legacy_post(&self, blog_identifier: &str, posts_action: PostAction, posts_type: PostType, optional_params: Option<LegacyPostOptionalParams>) -> TumblrFuture<PostId> {
+        self.response(self.client
+            .legacy_post_request(blog_identifier, posts_action, posts_type, optional_params)
+            .map(|(request, _)| request))
+    }
+
+    pub fn blog_posts_stream(&self, blog_identifier: &str, optional_params: Option<GetBlogPostsOptionalParams>) -> TumblrStream<Post> { self.paginate(paginate::blog_posts(blog_identifier, optional_params)) }
+
+    pub fn queue_stream(&self, blog_identifier: &str, optional_params: Option<GetBlogPostsQueueOptionalParams>) -> TumblrStream<Post> { self.paginate(paginate::queue(blog_identifier, optional_params)) }
+
+    pub fn drafts_stream(&self, blog_identifier: &str, optional_params: Option<GetBlogPostsDraftOptionalParams>) -> TumblrStream<Post> { self.paginate(paginate::drafts(blog_identifier, optional_params)) }
+
+    pub fn submissions_stream(&self, blog_identifier: &str, optional_params: Option<GetBlogPostsSubmissionOptionalParams>) -> TumblrStream<Post> { self.paginate(paginate::submissions(blog_identifier, optional_params)) }
+
+    pub fn blog_likes_stream(&self, blog_identifier: &str, optional_params: Option<GetBlogLikesOptionalParams>) -> TumblrStream<Post> { self.paginate(paginate::blog_likes(blog_identifier, optional_params)) }
+
+    pub fn blog_following_stream(&self, blog_identifier: &str, optional_params: Option<GetBlogFollowingOptionalParams>) -> TumblrStream<Blog> { self.paginate(paginate::blog_following(blog_identifier, optional_params)) }
+
+    pub fn followers_stream(&self, blog_identifier: &str, optional_params: Option<GetBlogFollowersOptionalParams>) -> TumblrStream<Follower> { self.paginate(paginate::followers(blog_identifier, optional_params)) }
+
+    pub fn dashboard_stream(&self, optional_params: Option<GetUserDashboardOptionalParams>) -> TumblrStream<Post> { self.paginate(paginate::dashboard(optional_params)) }
+
+    pub fn user_likes_stream(&self, optional_params: Option<GetUserLikesOptionalParams>) -> TumblrStream<Post> { self.paginate(paginate::user_likes(optional_params)) }
+
+    pub fn user_following_stream(&self, optional_params: Option<GetUserFollowingOptionalParams>) -> TumblrStream<Blog> { self.paginate(paginate::user_following(optional_params)) }
+
+    /// Stream every item of `pager`, fetching the next page once the previous one has been consumed.
+    fn paginate<T: Send + 'static>(&self, pager: Pager<T>) -> TumblrStream<T> {
+        let client = self.clone();
+        let pages = stream::unfold(Cursor::new(pager.start), move |cursor| -> Option<TumblrFuture<(Vec<T>, Cursor)>> {
+            let position = cursor.position()?;
+            let page = pager.page;
+            let sender = client.sender();
+
+            Some(Box::new(future::result(pager.request(&client.client, position))
+                .and_then(move |request| sender.send(request))
+                .and_then(json)
+                .and_then(move |value| page(value, position))
+                .map(move |page| (page.items, Cursor::advance(position, page.next)))))
+        });
+
+        Box::new(pages.map(stream::iter_ok).flatten())
+    }
+
+    pub fn create_npf_post(&self, blog_identifier: &str, content: &[ContentBlock], optional_params: Option<NpfPostOptionalParams>) -> TumblrFuture<PostId> {
+        self.create_npf_post_with_media(blog_identifier, NpfContent::blocks(content), optional_params)
+    }
+
+    pub fn create_npf_post_with_media(&self, blog_identifier: &str, content: Vec<NpfContent>, optional_params: Option<NpfPostOptionalParams>) -> TumblrFuture<PostId> {
+        self.response(self.client
+            .create_npf_post_request(blog_identifier, content, optional_params)
+            .map(|(request, _)| request))
+    }
+
+    pub fn edit_npf_post(&self, blog_identifier: &str, post_id: &str, content: &[ContentBlock], optional_params: Option<NpfPostOptionalParams>) -> TumblrFuture<PostId> {
+        self.edit_npf_post_with_media(blog_identifier, post_id, NpfContent::blocks(content), optional_params)
+    }
+
+    pub fn edit_npf_post_with_media(&self, blog_identifier: &str, post_id: &str, content: Vec<NpfContent>, optional_params: Option<NpfPostOptionalParams>) -> TumblrFuture<PostId> {
+        self.response(self.client
+            .edit_npf_post_request(blog_identifier, post_id, content, optional_params)
+            .map(|(request, _)| request))
+    }
+
+    pub fn reblog_npf_post(&self, blog_identifier: &str, parent: NpfReblog, content: &[ContentBlock], optional_params: Option<NpfPostOptionalParams>) -> TumblrFuture<PostId> {
+        self.reblog_npf_post_with_media(blog_identifier, parent, NpfContent::blocks(content), optional_params)
+    }
+
+    pub fn reblog_npf_post_with_media(&self, blog_identifier: &str, parent: NpfReblog, content: Vec<NpfContent>, optional_params: Option<NpfPostOptionalParams>) -> TumblrFuture<PostId> {
+        self.response(self.client
+            .reblog_npf_post_request(blog_identifier, parent, content, optional_params)
+            .map(|(request, _)| request))
+    }
+
+    /// See `TumblrClient::call`.
+    pub fn call<E: Endpoint>(&self, endpoint: E) -> TumblrFuture<E::Response> where E::Response: Send + 'static { self.response(self.client.endpoint_request(endpoint)) }
+
+    /// See `TumblrClient::call_raw`.
+    pub fn call_raw<E: Endpoint>(&self, endpoint: E) -> TumblrFuture<Response> {
+        match self.client.endpoint_request(endpoint) {
+            Ok(request) => self.sender().send(request),
+            Err(e) => Box::new(future::err(e)),
+        }
+    }
+
+    /// See `TumblrClient::request`.
+    pub fn request(&self, method: Method, path: &str, params: &[(&str, &str)], body: RequestBody) -> TumblrFuture<Response> {
+        self.request_with(self.client.default_auth(), method, path, params, body)
+    }
+
+    /// See `TumblrClient::request_with`.
+    pub fn request_with(&self, auth: Auth, method: Method, path: &str, params: &[(&str, &str)], body: RequestBody) -> TumblrFuture<Response> {
+        match self.client.raw_request(auth, method, path, params, body) {
+            Ok(request) => self.sender().send(request),
+            Err(e) => Box::new(future::err(e)),
+        }
+    }
+
+    /// See `TumblrClient::request_json`.
+    pub fn request_json<T: DeserializeOwned + Send + 'static>(&self, method: Method, path: &str, params: &[(&str, &str)], body: RequestBody) -> TumblrFuture<T> {
+        self.response(self.client.raw_request(self.client.default_auth(), method, path, params, body))
+    }
+
+    fn response<T: DeserializeOwned + Send + 'static>(&self, request: Result<Request, TumblrClientError>) -> TumblrFuture<T> {
+        match request {
+            Ok(request) => Box::new(self.sender().send(request).and_then(json).and_then(response)),
+            Err(e) => Box::new(future::err(e)),
+        }
+    }
+
+    fn sender(&self) -> Sender {
+        Sender {
+            session: self.session.clone(),
+            keys: self.client.keys.clone(),
+            retry_policy: self.client.retry_policy.for_request(&self.client.write_policy, false),
+            idempotent_retry_policy: self.client.retry_policy.clone(),
+            rate_limiter: self.client.rate_limiter.clone(),
+            oauth2: self.client.oauth2.clone(),
+            token_url: self.client.oauth2_token_url(),
+            on_unauthorized: self.client.on_unauthorized.clone(),
+        }
+    }
+}
+
+/// Everything a request future needs from the client, so it can outlive the borrow.
+#[derive(Clone)]
+struct Sender {
+    session: Client,
+    keys: Keys,
+    retry_policy: RetryPolicy,
+    idempotent_retry_policy: RetryPolicy,
+    rate_limiter: RateLimiter,
+    oauth2: Option<Arc<OAuth2>>,
+    token_url: String,
+    on_unauthorized: Option<Hook<str>>,
+}
+
+impl Sender {
+    fn send(self, request: Request) -> TumblrFuture<Response> {
+        let url = request.url.clone();
+        let on_unauthorized = self.on_unauthorized.clone();
+
+        let resp = self.authorize(request).and_then(move |request| {
+            let sender = self.clone();
+            let used = self.oauth2.as_ref().map(|oauth2| oauth2.token().access_token);
+
+            self.retry(request.clone()).and_then(move |resp| -> TumblrFuture<Response> {
+                match sender.oauth2 {
+                    Some(ref oauth2) if resp.status() == StatusCode::UNAUTHORIZED && oauth2.refreshable() && is_bearer(&request) => {
+                        let retry = sender.clone();
+
+                        Box::new(sender.refresh(used)
+                            .and_then(move |_| retry.authorize(request))
+                            .and_then(move |request| sender.retry(request)))
+                    }
+                    _ => Box::new(future::ok(resp)),
+                }
+            })
+        });
+
+        Box::new(resp.inspect(move |resp| {
+            if let Some(ref on_unauthorized) = on_unauthorized {
+                if resp.status() == StatusCode::UNAUTHORIZED { on_unauthorized.call(&url); }
+            }
+        }))
+    }
+
+    /// See `TumblrClient::authorize`.
+    fn authorize(&self, request: Request) -> TumblrFuture<Request> {
+        let oauth2 = match self.oauth2 {
+            Some(ref oauth2) if is_bearer(&request) => oauth2.clone(),
+            _ => return Box::new(future::ok(request)),
+        };
+        let refresh = match oauth2.expired() {
+            Some(stale) => self.refresh(Some(stale)),
+            None => Box::new(future::ok(())),
+        };
+
+        Box::new(refresh.and_then(move |_| {
+            let mut request = request;
+            request.headers.extend(oauth2.headers()?);
+
+            Ok(request)
+        }))
+    }
+
+    /// See `TumblrClient::refresh_stale_oauth2_token`. The refresh lock can't be held across a future, so the token is only
+    /// checked for a refresh by another request before this one starts.
+    fn refresh(&self, stale: Option<String>) -> TumblrFuture<()> {
+        let oauth2 = match self.oauth2 {
+            Some(ref oauth2) => oauth2.clone(),
+            None => return Box::new(future::err(TumblrClientError::OAuth { msg: "no OAuth2 token".to_owned() })),
+        };
+        if stale.is_some_and(|stale| oauth2.token().access_token != stale) { return Box::new(future::ok(())); }
+        let request = match oauth2.refresh_request(self.token_url.clone(), &self.keys) {
+            Ok(request) => request,
+            Err(e) => return Box::new(future::err(e)),
+        };
+
+        Box::new(self.clone()
+            .retry(request)
+            .and_then(|mut resp| {
+                let status = resp.status();
+
+                resp.text()
+                    .from_err()
+                    .and_then(move |body| oauth2::parse_token(status, &body))
+                    .map(move |token| oauth2.update(token))
+            }))
+    }
+
+    fn retry(self, request: Request) -> TumblrFuture<Response> {
+        let started = Instant::now();
+        let idempotent = request.idempotent();
+
+        Box::new(future::loop_fn(1, move |attempt| -> TumblrFuture<Loop<Response, u32>> {
+            let retry_policy = if idempotent { self.idempotent_retry_policy.clone() } else { self.retry_policy.clone() };
+
+            Box::new(self.dispatch(&request, retry_policy.deadline_at(started)).and_then(move |resp| -> TumblrFuture<Loop<Response, u32>> {
+                let retry = match classify(resp) {
+                    Attempt::Done(resp) => return Box::new(future::result(resp.map(Loop::Break).map_err(TumblrClientError::from))),
+                    Attempt::Rejected(resp) => resp,
+                    Attempt::Ambiguous(resp) => if idempotent { resp } else {
+                        return Box::new(future::result(resp.map(Loop::Break).map_err(TumblrClientError::from)));
+                    },
+                };
+
+                match retry_policy.next_delay(started, attempt, retry.as_ref().ok().and_then(|resp| retry_after(resp.headers()))) {
+                    Some(delay) => Box::new(sleep(delay).map(move |_| Loop::Continue(attempt + 1))),
+                    None => Box::new(future::result(retry.map(Loop::Break).map_err(TumblrClientError::from))),
+                }
+            }))
+        }))
+    }
+
+    /// See `TumblrClient::dispatch`.
+    fn dispatch(&self, request: &Request, deadline: Option<Instant>) -> TumblrFuture<reqwest::Result<Response>> {
+        let request = match request.build_async(&self.session) {
+            Ok(request) => request,
+            Err(e) => return Box::new(future::err(e)),
+        };
+        let wait = match self.rate_limiter.wait(&self.keys.consumer_key, deadline) {
+            Ok(wait) => wait.unwrap_or_default(),
+            Err(e) => return Box::new(future::err(e)),
+        };
+        let consumer_key = self.keys.consumer_key.clone();
+        let rate_limiter = self.rate_limiter.clone();
+
+        Box::new(sleep(wait).and_then(move |_| request.send().then(move |resp| {
+            if let Ok(ref resp) = resp { rate_limiter.record(&consumer_key, resp.headers()); }
+
+            Ok(resp)
+        })))
+    }
+}
+
+fn sleep(duration: Duration) -> TumblrFuture<()> {
+    if duration == Duration::default() { return Box::new(future::ok(())); }
+
+    Box::new(Delay::new(Instant::now() + duration).from_err())
+}
+
+fn json(mut resp: Response) -> TumblrFuture<Value> {
+    let status = resp.status();
+
+    Box::new(resp.text()
+        .from_err()
+        .and_then(move |body| parse_body(status, body)))
+}
+
+fn token_text(mut resp: Response) -> TumblrFuture<String> {
+    let status = resp.status();
+
+    Box::new(resp.text()
+        .from_err()
+        .and_then(move |body| token_body(status, body)))
+}
@@ -1,566 +1,1702 @@
+// --- std ---
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::Read,
+};
 // --- external ---
-use serde_json::Value;
-use reqwest::multipart::Form;
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
 // --- custom ---
-use super::{TumblrClient, build_oauth_headers, build_query, build_params};
+use super::{
+    TumblrClient, TumblrClientError, parse_json, response,
+    endpoint::Endpoint,
+    model::{BlogInfo, Followers, Following, Likes, PostId, Posts},
+    params::{check_exclusive, check_limit, check_tags, gmt, iso8601, join_tags, timestamp},
+    raw::{Auth, FormField, RequestBody},
+    request::{Part, PartData, Request},
+    retry::{PostMatch, WritePolicy},
+};
 
-const BLOG: &'static str = "https://api.tumblr.com/v2/blog/";
+const BLOG: &str = "blog/";
+/// The sizes Tumblr serves avatars in, square.
+const AVATAR_SIZES: &[u32] = &[16, 24, 30, 40, 48, 64, 96, 128, 512];
 
-#[derive(Default)]
-pub struct GetBlogAvatarOptionalParams<'a> { size: Option<&'a str> }
+/// Implements `Display` for an enum param, as the value Tumblr expects.
+macro_rules! param_enum {
+    ($name:ident { $($variant:ident = $value:literal,)* }) => {
+        impl $name {
+            pub fn as_str(self) -> &'static str {
+                match self { $($name::$variant => $value,)* }
+            }
+        }
 
-impl<'a> GetBlogAvatarOptionalParams<'a> {
-    pub fn new() -> GetBlogAvatarOptionalParams<'a> { GetBlogAvatarOptionalParams::default() }
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.as_str()) }
+        }
+    };
+}
 
-    pub fn size(mut self, size: &'a str) -> Self {
-        match size {
-            "16" | "24" | "30" | "40" | "48" | "64" | "96" | "128" | "512" => {
-                self.size = Some(size);
-                self
-            }
-            _ => panic!("The size of the avatar (square, one value for both length and width). Must be one of the values: 16, 24, 30, 40, 48, 64, 96, 128, 512")
+/// The type of the posts to return.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostKind {
+    Text,
+    Quote,
+    Link,
+    Answer,
+    Video,
+    Audio,
+    Photo,
+    Chat,
+}
+
+param_enum!(PostKind {
+    Text = "text",
+    Quote = "quote",
+    Link = "link",
+    Answer = "answer",
+    Video = "video",
+    Audio = "audio",
+    Photo = "photo",
+    Chat = "chat",
+});
+
+/// The format of the text of returned posts, HTML when unset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Filter {
+    /// Plain text, without HTML.
+    Text,
+    /// As the author entered it.
+    Raw,
+}
+
+param_enum!(Filter {
+    Text = "text",
+    Raw = "raw",
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostState {
+    Published,
+    Draft,
+    Queue,
+    Private,
+}
+
+param_enum!(PostState {
+    Published = "published",
+    Draft = "draft",
+    Queue = "queue",
+    Private = "private",
+});
+
+/// The format of the text of a legacy post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextFormat {
+    Html,
+    Markdown,
+}
+
+param_enum!(TextFormat {
+    Html = "html",
+    Markdown = "markdown",
+});
+
+/// Who can reblog an NPF post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interactability {
+    Everyone,
+    Noone,
+}
+
+param_enum!(Interactability {
+    Everyone = "everyone",
+    Noone = "noone",
+});
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetBlogAvatarOptionalParams { size: Option<u32> }
+
+impl GetBlogAvatarOptionalParams {
+    pub fn new() -> GetBlogAvatarOptionalParams { GetBlogAvatarOptionalParams::default() }
+
+    // One of 16, 24, 30, 40, 48, 64, 96, 128, 512
+    set_attr!(self, size: u32);
+
+    fn validate(&self) -> Result<(), TumblrClientError> {
+        match self.size {
+            Some(size) if !AVATAR_SIZES.contains(&size) => Err(TumblrClientError::InvalidInput { msg: format!("the size of an avatar must be one of {:?}, got {}", AVATAR_SIZES, size) }),
+            _ => Ok(()),
         }
     }
 }
 
-#[derive(Default)]
-pub struct GetBlogLikesOptionalParams<'a> {
-    limit: Option<&'a str>,
-    offset: Option<&'a str>,
-    before: Option<&'a str>,
-    after: Option<&'a str>,
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetBlogLikesOptionalParams {
+    pub(super) limit: Option<u32>,
+    pub(super) offset: Option<u64>,
+    pub(super) before: Option<DateTime<Utc>>,
+    pub(super) after: Option<DateTime<Utc>>,
 }
 
-impl<'a> GetBlogLikesOptionalParams<'a> {
-    pub fn new() -> GetBlogLikesOptionalParams<'a> { GetBlogLikesOptionalParams::default() }
+impl GetBlogLikesOptionalParams {
+    pub fn new() -> GetBlogLikesOptionalParams { GetBlogLikesOptionalParams::default() }
+
+    set_attr!(self, limit: u32);
+    set_attr!(self, offset: u64);
+    set_attr!(self, before: DateTime<Utc>);
+    set_attr!(self, after: DateTime<Utc>);
 
-    set_attr!(self, limit);
-    set_attr!(self, offset);
-    set_attr!(self, before);
-    set_attr!(self, after);
+    fn validate(&self) -> Result<(), TumblrClientError> {
+        check_limit(self.limit)?;
+        check_exclusive(&[("offset", self.offset.is_some()), ("before", self.before.is_some()), ("after", self.after.is_some())])
+    }
 }
 
-#[derive(Default)]
-pub struct GetBlogFollowingOptionalParams<'a> {
-    limit: Option<&'a str>,
-    offset: Option<&'a str>,
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetBlogFollowingOptionalParams {
+    pub(super) limit: Option<u32>,
+    pub(super) offset: Option<u64>,
 }
 
-impl<'a> GetBlogFollowingOptionalParams<'a> {
-    pub fn new() -> GetBlogFollowingOptionalParams<'a> { GetBlogFollowingOptionalParams::default() }
+impl GetBlogFollowingOptionalParams {
+    pub fn new() -> GetBlogFollowingOptionalParams { GetBlogFollowingOptionalParams::default() }
 
-    set_attr!(self, limit);
-    set_attr!(self, offset);
+    set_attr!(self, limit: u32);
+    set_attr!(self, offset: u64);
 }
 
-#[derive(Default)]
-pub struct GetBlogFollowersOptionalParams<'a> {
-    limit: Option<&'a str>,
-    offset: Option<&'a str>,
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetBlogFollowersOptionalParams {
+    pub(super) limit: Option<u32>,
+    pub(super) offset: Option<u64>,
 }
 
-impl<'a> GetBlogFollowersOptionalParams<'a> {
-    pub fn new() -> GetBlogFollowersOptionalParams<'a> { GetBlogFollowersOptionalParams::default() }
+impl GetBlogFollowersOptionalParams {
+    pub fn new() -> GetBlogFollowersOptionalParams { GetBlogFollowersOptionalParams::default() }
 
-    set_attr!(self, limit);
-    set_attr!(self, offset);
+    set_attr!(self, limit: u32);
+    set_attr!(self, offset: u64);
 }
 
-#[derive(Default)]
-pub struct GetBlogPostsOptionalParams<'a> {
-    r#type: Option<&'a str>,
-    id: Option<&'a str>,
-    tag: Option<&'a str>,
-    limit: Option<&'a str>,
-    offset: Option<&'a str>,
-    reblog_info: Option<&'a str>,
-    notes_info: Option<&'a str>,
-    filter: Option<&'a str>,
-    before: Option<&'a str>,
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetBlogPostsOptionalParams {
+    r#type: Option<PostKind>,
+    id: Option<u64>,
+    tag: Option<String>,
+    pub(super) limit: Option<u32>,
+    pub(super) offset: Option<u64>,
+    reblog_info: Option<bool>,
+    notes_info: Option<bool>,
+    filter: Option<Filter>,
+    before: Option<DateTime<Utc>>,
+    npf: Option<bool>,
 }
 
-impl<'a> GetBlogPostsOptionalParams<'a> {
-    pub fn new() -> GetBlogPostsOptionalParams<'a> { GetBlogPostsOptionalParams::default() }
+impl GetBlogPostsOptionalParams {
+    pub fn new() -> GetBlogPostsOptionalParams { GetBlogPostsOptionalParams::default() }
 
-    set_attr!(self, r#type);
-    set_attr!(self, id);
+    set_attr!(self, r#type: PostKind);
+    set_attr!(self, id: u64);
     set_attr!(self, tag);
-    set_attr!(self, limit);
-    set_attr!(self, offset);
-    set_attr!(self, reblog_info);
-    set_attr!(self, notes_info);
-    set_attr!(self, filter);
-    set_attr!(self, before);
+    set_attr!(self, limit: u32);
+    set_attr!(self, offset: u64);
+    set_attr!(self, reblog_info: bool);
+    set_attr!(self, notes_info: bool);
+    set_attr!(self, filter: Filter);
+    set_attr!(self, before: DateTime<Utc>);
+    set_attr!(self, npf: bool);
 }
 
-#[derive(Default)]
-pub struct GetBlogPostsQueueOptionalParams<'a> {
-    limit: Option<&'a str>,
-    offset: Option<&'a str>,
-    filter: Option<&'a str>,
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetBlogPostsQueueOptionalParams {
+    pub(super) limit: Option<u32>,
+    pub(super) offset: Option<u64>,
+    filter: Option<Filter>,
+    npf: Option<bool>,
 }
 
-impl<'a> GetBlogPostsQueueOptionalParams<'a> {
-    pub fn new() -> GetBlogPostsQueueOptionalParams<'a> { GetBlogPostsQueueOptionalParams::default() }
+impl GetBlogPostsQueueOptionalParams {
+    pub fn new() -> GetBlogPostsQueueOptionalParams { GetBlogPostsQueueOptionalParams::default() }
 
-    set_attr!(self, limit);
-    set_attr!(self, offset);
-    set_attr!(self, filter);
+    set_attr!(self, limit: u32);
+    set_attr!(self, offset: u64);
+    set_attr!(self, filter: Filter);
+    set_attr!(self, npf: bool);
 }
 
-#[derive(Default)]
-pub struct GetBlogPostsDraftOptionalParams<'a> {
-    before_id: Option<&'a str>,
-    filter: Option<&'a str>,
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetBlogPostsDraftOptionalParams {
+    before_id: Option<u64>,
+    filter: Option<Filter>,
+    npf: Option<bool>,
 }
 
-impl<'a> GetBlogPostsDraftOptionalParams<'a> {
-    pub fn new() -> GetBlogPostsDraftOptionalParams<'a> { GetBlogPostsDraftOptionalParams::default() }
+impl GetBlogPostsDraftOptionalParams {
+    pub fn new() -> GetBlogPostsDraftOptionalParams { GetBlogPostsDraftOptionalParams::default() }
 
-    set_attr!(self, before_id);
-    set_attr!(self, filter);
+    set_attr!(self, before_id: u64);
+    set_attr!(self, filter: Filter);
+    set_attr!(self, npf: bool);
 }
 
-#[derive(Default)]
-pub struct GetBlogPostsSubmissionOptionalParams<'a> {
-    offset: Option<&'a str>,
-    filter: Option<&'a str>,
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetBlogPostsSubmissionOptionalParams {
+    pub(super) offset: Option<u64>,
+    filter: Option<Filter>,
 }
 
-impl<'a> GetBlogPostsSubmissionOptionalParams<'a> {
-    pub fn new() -> GetBlogPostsSubmissionOptionalParams<'a> { GetBlogPostsSubmissionOptionalParams::default() }
+impl GetBlogPostsSubmissionOptionalParams {
+    pub fn new() -> GetBlogPostsSubmissionOptionalParams { GetBlogPostsSubmissionOptionalParams::default() }
 
-    set_attr!(self, offset);
-    set_attr!(self, filter);
+    set_attr!(self, offset: u64);
+    set_attr!(self, filter: Filter);
 }
 
-pub enum PostAction<'a> {
+/// What a legacy post does: create a post, edit the post with the given id, or reblog.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostAction {
     New,
-    Edit(&'a str),
+    Edit(String),
     Reblog {
-        id: &'a str,
-        reblog_key: &'a str,
-        comment: &'a str,
-        native_inline_images: &'a str,
+        id: String,
+        reblog_key: String,
+        comment: String,
+        native_inline_images: bool,
     },
 }
 
-pub enum PostType<'a> {
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PostType {
     Text {
-        title: Option<&'a str>,
-        body: &'a str,
+        title: Option<String>,
+        body: String,
     },
     Photo {
-        caption: Option<&'a str>,
-        link: Option<&'a str>,
-        source: Option<&'a str>,
-        data: Option<Vec<&'a str>>,
-        data64: Option<&'a str>,
+        caption: Option<String>,
+        link: Option<String>,
+        source: Option<String>,
+        data: Option<Vec<String>>,
+        data64: Option<String>,
     },
     Quote {
-        quote: &'a str,
-        source: Option<&'a str>,
+        quote: String,
+        source: Option<String>,
     },
     Link {
-        title: Option<&'a str>,
-        url: &'a str,
-        description: Option<&'a str>,
-        thumbnail: Option<&'a str>,
-        excerpt: Option<&'a str>,
-        author: Option<&'a str>,
+        title: Option<String>,
+        url: String,
+        description: Option<String>,
+        thumbnail: Option<String>,
+        excerpt: Option<String>,
+        author: Option<String>,
     },
     Chat {
-        title: Option<&'a str>,
-        conversation: &'a str,
+        title: Option<String>,
+        conversation: String,
     },
     Audio {
-        caption: Option<&'a str>,
-        external_url: Option<&'a str>,
-        data: Option<&'a str>,
+        caption: Option<String>,
+        external_url: Option<String>,
+        data: Option<String>,
     },
     Video {
-        caption: Option<&'a str>,
-        embed: Option<&'a str>,
-        data: Option<&'a str>,
+        caption: Option<String>,
+        embed: Option<String>,
+        data: Option<String>,
+    },
+}
+
+/// Implements serde for an NPF enum tagged by `type`, keeping blocks this crate can't parse as `Unknown`.
+///
+/// The NPF structs likewise keep the fields they don't model in `extra`, so that they are sent back unchanged.
+macro_rules! npf_enum {
+    ($name:ident { $($variant:ident($ty:ty) = $tag:literal,)* }) => {
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                #[derive(Serialize)]
+                #[serde(tag = "type")]
+                enum Tagged<'a> { $(#[serde(rename = $tag)] $variant(&'a $ty),)* }
+
+                match self {
+                    $($name::$variant(inner) => Tagged::$variant(inner).serialize(serializer),)*
+                    $name::Unknown(value) => value.serialize(serializer),
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = Value::deserialize(deserializer)?;
+                // `type` is the tag, not one of the unmodeled fields of the variant
+                let mut fields = value.clone();
+                if let Some(fields) = fields.as_object_mut() { fields.remove("type"); }
+                let parsed = match value["type"].as_str() {
+                    $(Some($tag) => <$ty>::deserialize(&fields).ok().map($name::$variant),)*
+                    _ => None,
+                };
+
+                Ok(parsed.unwrap_or($name::Unknown(value)))
+            }
+        }
+    };
+}
+
+/// An NPF content block.
+///
+/// Blocks of a type this crate does not know, or which don't match their type's shape, are kept as `Unknown`
+/// and serialized back unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentBlock {
+    Text(TextBlock),
+    Image(ImageBlock),
+    Link(LinkBlock),
+    Audio(AudioBlock),
+    Video(VideoBlock),
+    Paywall(PaywallBlock),
+    Poll(PollBlock),
+    Unknown(Value),
+}
+
+npf_enum!(ContentBlock {
+    Text(TextBlock) = "text",
+    Image(ImageBlock) = "image",
+    Link(LinkBlock) = "link",
+    Audio(AudioBlock) = "audio",
+    Video(VideoBlock) = "video",
+    Paywall(PaywallBlock) = "paywall",
+    Poll(PollBlock) = "poll",
+});
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextBlock {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtype: Option<TextSubtype>,
+    /// Nesting of list items and indented blocks, from `0` to `7`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indent_level: Option<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formatting: Vec<Formatting>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl TextBlock {
+    pub fn new(text: &str) -> TextBlock {
+        TextBlock {
+            text: text.to_owned(),
+            ..TextBlock::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextSubtype {
+    Heading1,
+    Heading2,
+    Quirky,
+    Quote,
+    Indented,
+    Chat,
+    OrderedListItem,
+    UnorderedListItem,
+}
+
+/// Inline formatting of the characters `start..end` of a text block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Formatting {
+    pub start: u64,
+    pub end: u64,
+    #[serde(flatten)]
+    pub style: Style,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Style {
+    Bold,
+    Italic,
+    Strikethrough,
+    Small,
+    Link { url: String },
+    Mention { blog: BlogRef },
+    /// A `#rrggbb` color.
+    Color { hex: String },
+}
+
+/// A blog as referenced from mentions and attributions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlogRef {
+    pub uuid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// A file hosted by Tumblr or elsewhere, or with `identifier`, a part of the upload it's sent with.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MediaObject {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// The MIME type.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_dimensions_missing: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cropped: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_original_dimensions: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Attribution {
+    Post {
+        url: String,
+        post: PostRef,
+        blog: BlogRef,
     },
+    Link { url: String },
+    Blog { blog: BlogRef },
+    App {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        app_name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        display_text: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        logo: Option<MediaObject>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PostRef { pub id: String }
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImageBlock {
+    pub media: Vec<MediaObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub colors: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poster: Option<MediaObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<Attribution>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alt_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LinkBlock {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub poster: Vec<MediaObject>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioBlock {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media: Option<MediaObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub poster: Vec<MediaObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embed_html: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embed_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<Attribution>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VideoBlock {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media: Option<MediaObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embed_html: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embed_iframe: Option<EmbedIframe>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embed_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub poster: Vec<MediaObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<Attribution>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_autoplay_on_cellular: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmbedIframe {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PaywallBlock {
+    /// `cta`, `divider` or `disabled`.
+    pub subtype: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PollBlock {
+    pub client_id: String,
+    pub question: String,
+    pub answers: Vec<PollAnswer>,
+    pub settings: PollSettings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PollAnswer {
+    pub client_id: String,
+    pub answer_text: String,
 }
 
-#[derive(Default)]
-pub struct LegacyPostOptionalParams<'a> {
-    state: Option<&'a str>,
-    tags: Option<&'a str>,
-    tweet: Option<&'a str>,
-    date: Option<&'a str>,
-    format: Option<&'a str>,
-    slug: Option<&'a str>,
-    native_inline_images: Option<&'a str>,
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PollSettings {
+    /// `closed-after` is the only value Tumblr accepts.
+    pub close_status: String,
+    /// Seconds the poll stays open.
+    pub expire_after: u64,
+    #[serde(default)]
+    pub multiple_choice: bool,
+    /// `tumblr` for polls made on Tumblr.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-impl<'a> LegacyPostOptionalParams<'a> {
-    pub fn new() -> LegacyPostOptionalParams<'a> { LegacyPostOptionalParams::default() }
+/// An NPF layout, arranging the content blocks by their index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Layout {
+    Rows(RowsLayout),
+    Condensed(CondensedLayout),
+    Ask(AskLayout),
+    Unknown(Value),
+}
+
+npf_enum!(Layout {
+    Rows(RowsLayout) = "rows",
+    Condensed(CondensedLayout) = "condensed",
+    Ask(AskLayout) = "ask",
+});
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RowsLayout {
+    pub display: Vec<Row>,
+    /// Index of the last block shown before a "Keep reading" link.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncate_after: Option<usize>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Row {
+    pub blocks: Vec<usize>,
+    /// `{"type": "weighted"}` or `{"type": "carousel"}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CondensedLayout {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncate_after: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocks: Vec<usize>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AskLayout {
+    pub blocks: Vec<usize>,
+    /// The asker, absent for anonymous asks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<Attribution>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LegacyPostOptionalParams {
+    state: Option<PostState>,
+    tags: Option<Vec<String>>,
+    tweet: Option<String>,
+    date: Option<DateTime<Utc>>,
+    format: Option<TextFormat>,
+    slug: Option<String>,
+    native_inline_images: Option<bool>,
+}
+
+impl LegacyPostOptionalParams {
+    pub fn new() -> LegacyPostOptionalParams { LegacyPostOptionalParams::default() }
 
-    set_attr!(self, state);
-    set_attr!(self, tags);
+    set_attr!(self, state: PostState);
+
+    pub fn tags<T: AsRef<str>>(mut self, tags: &[T]) -> Self {
+        self.tags = Some(tags.iter().map(|tag| tag.as_ref().to_owned()).collect());
+        self
+    }
+
+    /// Add `tag` to the tags.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.get_or_insert_with(Vec::new).push(tag.to_owned());
+        self
+    }
     set_attr!(self, tweet);
-    set_attr!(self, date);
-    set_attr!(self, format);
+    set_attr!(self, date: DateTime<Utc>);
+    set_attr!(self, format: TextFormat);
     set_attr!(self, slug);
-    set_attr!(self, native_inline_images);
+    set_attr!(self, native_inline_images: bool);
 }
 
-impl TumblrClient {
-    pub fn get_blog_info(&self, blog_identifier: &str) -> Value {
-        let url = format!("{}{}/info?api_key={}", BLOG, blog_identifier, self.keys.consumer_key);
-        self.get(&url, None)
-            .json()
-            .unwrap()
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NpfPostOptionalParams {
+    state: Option<PostState>,
+    publish_on: Option<DateTime<Utc>>,
+    date: Option<DateTime<Utc>>,
+    tags: Option<Vec<String>>,
+    source_url: Option<String>,
+    send_to_twitter: Option<bool>,
+    is_private: Option<bool>,
+    slug: Option<String>,
+    interactability_reblog: Option<Interactability>,
+    layout: Option<Vec<Layout>>,
+}
+
+impl NpfPostOptionalParams {
+    pub fn new() -> NpfPostOptionalParams { NpfPostOptionalParams::default() }
+
+    set_attr!(self, state: PostState);
+    // requires `PostState::Queue`
+    set_attr!(self, publish_on: DateTime<Utc>);
+    set_attr!(self, date: DateTime<Utc>);
+
+    pub fn tags<T: AsRef<str>>(mut self, tags: &[T]) -> Self {
+        self.tags = Some(tags.iter().map(|tag| tag.as_ref().to_owned()).collect());
+        self
     }
 
-    pub fn get_blog_avatar(&self, blog_identifier: &str, optional_params: Option<GetBlogAvatarOptionalParams>) -> Vec<u8> {
-        let mut url = format!("{}{}/avatar/", BLOG, blog_identifier);
-        if let Some(optional_params) = optional_params {
-            if let Some(size) = optional_params.size { url += size; }
+    /// Add `tag` to the tags.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.get_or_insert_with(Vec::new).push(tag.to_owned());
+        self
+    }
+    set_attr!(self, source_url);
+    set_attr!(self, send_to_twitter: bool);
+    set_attr!(self, is_private: bool);
+    set_attr!(self, slug);
+    set_attr!(self, interactability_reblog: Interactability);
+
+    pub fn layout(mut self, layout: &[Layout]) -> Self {
+        self.layout = Some(layout.to_vec());
+        self
+    }
+
+    /// The string params, sent as fields of the JSON body.
+    fn params(&self) -> Vec<(&str, String)> {
+        set_params![
+            ("state", self.state),
+            ("publish_on", self.publish_on.as_ref().map(iso8601)),
+            ("date", self.date.as_ref().map(iso8601)),
+            ("tags", self.tags.as_deref().map(join_tags)),
+            ("source_url", self.source_url.as_ref()),
+            ("slug", self.slug.as_ref()),
+            ("interactability_reblog", self.interactability_reblog)
+        ]
+    }
+
+    fn validate(&self) -> Result<(), TumblrClientError> {
+        check_tags(self.tags.as_deref())?;
+        if self.publish_on.is_some() && self.state != Some(PostState::Queue) {
+            return Err(TumblrClientError::InvalidInput { msg: "publish_on requires the queue state".to_owned() });
         }
 
-        let mut bytes = vec![];
-        self.get(&url, None)
-            .copy_to(&mut bytes)
-            .unwrap();
-
-        bytes
-    }
-
-    pub fn get_blog_likes(&self, blog_identifier: &str, optional_params: Option<GetBlogLikesOptionalParams>) -> Value {
-        let mut url = format!("{}{}/likes?api_key={}", BLOG, blog_identifier, self.keys.consumer_key);
-        if let Some(optional_params) = optional_params {
-            build_url!(
-                url,
-                [
-                    ("limit", optional_params.limit),
-                    ("offset", optional_params.offset),
-                    ("before", optional_params.before),
-                    ("after", optional_params.after)
-                ]
-            );
+        Ok(())
+    }
+}
+
+/// The post an NPF reblog is made from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NpfReblog {
+    pub parent_tumblelog_uuid: String,
+    pub parent_post_id: String,
+    pub reblog_key: String,
+}
+
+/// A file uploaded with an NPF post, read once when the request is built.
+pub struct Media {
+    data: MediaData,
+    mime_type: String,
+    file_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+enum MediaData {
+    Bytes(Vec<u8>),
+    Reader(Box<dyn Read + Send>),
+    File(String),
+}
+
+impl Media {
+    pub fn bytes(bytes: Vec<u8>, mime_type: &str) -> Media { Media::new(MediaData::Bytes(bytes), mime_type) }
+
+    pub fn reader<R: Read + Send + 'static>(reader: R, mime_type: &str) -> Media { Media::new(MediaData::Reader(Box::new(reader)), mime_type) }
+
+    pub fn file(path: &str, mime_type: &str) -> Media { Media::new(MediaData::File(path.to_owned()), mime_type) }
+
+    fn new(data: MediaData, mime_type: &str) -> Media {
+        Media {
+            data,
+            mime_type: mime_type.to_owned(),
+            file_name: None,
+            width: None,
+            height: None,
+        }
+    }
+
+    pub fn file_name(mut self, file_name: &str) -> Self {
+        self.file_name = Some(file_name.to_owned());
+        self
+    }
+
+    pub fn dimensions(mut self, width: u32, height: u32) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+
+    fn media_object(&self, identifier: &str) -> MediaObject {
+        MediaObject {
+            mime_type: Some(self.mime_type.to_owned()),
+            width: self.width,
+            height: self.height,
+            identifier: Some(identifier.to_owned()),
+            ..MediaObject::default()
         }
+    }
 
-        self.get(&url, None)
-            .json()
-            .unwrap()
+    pub(super) fn into_part(self, identifier: String) -> Result<Part, TumblrClientError> {
+        let data = match self.data {
+            MediaData::Bytes(bytes) => PartData::Bytes(bytes),
+            MediaData::Reader(mut reader) => {
+                let mut bytes = vec![];
+                reader.read_to_end(&mut bytes).map_err(|e| TumblrClientError::InvalidInput { msg: format!("{}: {}", identifier, e) })?;
+
+                PartData::Bytes(bytes)
+            }
+            MediaData::File(path) => PartData::File(path),
+        };
+        let file_name = match data {
+            PartData::File(_) => self.file_name,
+            _ => Some(self.file_name.unwrap_or_else(|| identifier.to_owned())),
+        };
+
+        Ok(Part {
+            name: identifier,
+            data,
+            mime_type: Some(self.mime_type),
+            file_name,
+        })
     }
+}
+
+/// A block of an NPF post, possibly with the media it uploads.
+pub enum NpfContent {
+    Block(ContentBlock),
+    /// An image, audio or video block, whose media is replaced by `Media`.
+    Upload(ContentBlock, Media),
+}
+
+impl NpfContent {
+    pub(super) fn blocks(blocks: &[ContentBlock]) -> Vec<NpfContent> {
+        blocks.iter()
+            .cloned()
+            .map(NpfContent::Block)
+            .collect()
+    }
+}
+
+impl From<ContentBlock> for NpfContent {
+    fn from(block: ContentBlock) -> Self { NpfContent::Block(block) }
+}
 
-    pub fn get_blog_following(&self, blog_identifier: &str, optional_params: Option<GetBlogFollowingOptionalParams>) -> Value {
-        let api = format!("{}{}/following", BLOG, blog_identifier);
-        let params = if let Some(optional_params) = optional_params {
+/// What an `NpfPost` does: create a post, edit the post with the given id, or reblog.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NpfAction {
+    New,
+    Edit(String),
+    Reblog(NpfReblog),
+}
+
+pub(super) struct PostLookup {
+    state: Option<String>,
+    needle: Needle,
+    /// Whether to look among posts fetched as NPF.
+    npf: bool,
+}
+
+/// What `PostMatch::BodyHash` recognizes a post by.
+enum Fingerprint<'a> {
+    /// A field of a legacy post.
+    Field(&'static str, &'a str),
+    /// The text blocks of an NPF post.
+    Text(String),
+}
+
+enum Needle {
+    Slug(String),
+    BodyHash(&'static str, u64),
+    TextHash(u64),
+    Tag(String),
+}
+
+impl PostLookup {
+    fn new(post_match: &PostMatch, params: &[(&str, String)], fingerprint: Option<Fingerprint>) -> Result<PostLookup, TumblrClientError> {
+        let param = |name| params.iter().find(|&&(k, _)| k == name).map(|(_, v)| v.to_owned());
+        let npf = matches!(fingerprint, Some(Fingerprint::Text(_)));
+        let needle = match post_match {
+            PostMatch::Slug => Needle::Slug(
+                param("slug")
+                    .map(|slug| normalize_slug(&slug))
+                    .ok_or_else(|| TumblrClientError::InvalidInput { msg: "PostMatch::Slug requires a slug".to_owned() })?
+            ),
+            PostMatch::BodyHash => match fingerprint {
+                Some(Fingerprint::Field(field, body)) => Needle::BodyHash(field, hash_body(body)),
+                Some(Fingerprint::Text(ref text)) if !text.trim().is_empty() => Needle::TextHash(hash_body(text)),
+                _ => return Err(TumblrClientError::InvalidInput { msg: "PostMatch::BodyHash requires a post body, caption or text block".to_owned() }),
+            },
+            PostMatch::Marker(marker) => Needle::Tag(marker.to_owned()),
+        };
+
+        Ok(PostLookup { state: param("state"), needle, npf })
+    }
+
+    fn matches(&self, post: &Value) -> bool {
+        match self.needle {
+            Needle::Slug(ref slug) => post["slug"].as_str() == Some(slug),
+            Needle::BodyHash(field, hash) => post[field].as_str().map(hash_body) == Some(hash),
+            Needle::TextHash(hash) => post["content"].as_array().map(|blocks| hash_body(&text_of(blocks.iter()
+                .filter(|block| block["type"] == "text")
+                .filter_map(|block| block["text"].as_str())))) == Some(hash),
+            Needle::Tag(ref tag) => post["tags"].as_array().is_some_and(|tags| tags.iter().any(|t| t.as_str() == Some(tag))),
+        }
+    }
+}
+
+/// The text of an NPF post, as `PostMatch::BodyHash` hashes it.
+fn text_of<'a>(texts: impl Iterator<Item = &'a str>) -> String { texts.collect::<Vec<_>>().join("\n") }
+
+fn normalize_slug(slug: &str) -> String {
+    slug.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
+fn hash_body(body: &str) -> u64 {
+    // --- std ---
+    use std::{
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+    };
+
+    let mut hasher = DefaultHasher::new();
+    for word in body.split_whitespace() { word.hash(&mut hasher); }
+
+    hasher.finish()
+}
+
+pub struct GetBlogInfo { pub blog_identifier: String }
+
+impl Endpoint for GetBlogInfo {
+    type Response = BlogInfo;
+
+    fn path(&self) -> String { format!("{}{}/info", BLOG, self.blog_identifier) }
+
+    fn auth(&self) -> Auth { Auth::ApiKey }
+}
+
+/// Answered with the image itself rather than JSON, so send it with `call_raw`.
+pub struct GetBlogAvatar {
+    pub blog_identifier: String,
+    pub optional_params: Option<GetBlogAvatarOptionalParams>,
+}
+
+impl Endpoint for GetBlogAvatar {
+    type Response = Vec<u8>;
+
+    fn path(&self) -> String {
+        match self.optional_params.as_ref().and_then(|optional_params| optional_params.size) {
+            Some(size) => format!("{}{}/avatar/{}", BLOG, self.blog_identifier, size),
+            None => format!("{}{}/avatar/", BLOG, self.blog_identifier),
+        }
+    }
+
+    fn auth(&self) -> Auth { Auth::None }
+
+    fn validate(&self) -> Result<(), TumblrClientError> { self.optional_params.as_ref().map_or(Ok(()), GetBlogAvatarOptionalParams::validate) }
+}
+
+pub struct GetBlogLikes {
+    pub blog_identifier: String,
+    pub optional_params: Option<GetBlogLikesOptionalParams>,
+}
+
+impl Endpoint for GetBlogLikes {
+    type Response = Likes;
+
+    fn path(&self) -> String { format!("{}{}/likes", BLOG, self.blog_identifier) }
+
+    fn auth(&self) -> Auth { Auth::ApiKey }
+
+    fn params(&self) -> Vec<(&str, String)> {
+        if let Some(ref optional_params) = self.optional_params {
+            set_params![
+                ("limit", optional_params.limit),
+                ("offset", optional_params.offset),
+                ("before", optional_params.before.as_ref().map(timestamp)),
+                ("after", optional_params.after.as_ref().map(timestamp))
+            ]
+        } else { vec![] }
+    }
+
+    fn validate(&self) -> Result<(), TumblrClientError> { self.optional_params.as_ref().map_or(Ok(()), GetBlogLikesOptionalParams::validate) }
+}
+
+pub struct GetBlogFollowing {
+    pub blog_identifier: String,
+    pub optional_params: Option<GetBlogFollowingOptionalParams>,
+}
+
+impl Endpoint for GetBlogFollowing {
+    type Response = Following;
+
+    fn path(&self) -> String { format!("{}{}/following", BLOG, self.blog_identifier) }
+
+    fn params(&self) -> Vec<(&str, String)> {
+        if let Some(ref optional_params) = self.optional_params {
             set_params![
                 ("limit", optional_params.limit),
                 ("offset", optional_params.offset)
             ]
-        } else { vec![] };
-        let url = build_query(&api, &params);
-        let headers = build_oauth_headers(
-            "GET",
-            &api,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(params)),
-        );
-
-        self.get(&url, Some(headers))
-            .json()
-            .unwrap()
-    }
-
-    pub fn get_blog_followers(&self, blog_identifier: &str, optional_params: Option<GetBlogFollowersOptionalParams>) -> Value {
-        let api = format!("{}{}/followers", BLOG, blog_identifier);
-        let params = if let Some(optional_params) = optional_params {
+        } else { vec![] }
+    }
+
+    fn validate(&self) -> Result<(), TumblrClientError> { check_limit(self.optional_params.as_ref().and_then(|optional_params| optional_params.limit)) }
+}
+
+pub struct GetBlogFollowers {
+    pub blog_identifier: String,
+    pub optional_params: Option<GetBlogFollowersOptionalParams>,
+}
+
+impl Endpoint for GetBlogFollowers {
+    type Response = Followers;
+
+    fn path(&self) -> String { format!("{}{}/followers", BLOG, self.blog_identifier) }
+
+    fn params(&self) -> Vec<(&str, String)> {
+        if let Some(ref optional_params) = self.optional_params {
             set_params![
                 ("limit", optional_params.limit),
                 ("offset", optional_params.offset)
             ]
-        } else { vec![] };
-        let url = build_query(&api, &params);
-        let headers = build_oauth_headers(
-            "GET",
-            &api,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(params)),
-        );
-
-        self.get(&url, Some(headers))
-            .json()
-            .unwrap()
-    }
-
-    pub fn get_blog_posts(&self, blog_identifier: &str, optional_params: Option<GetBlogPostsOptionalParams>) -> Value {
-        let url = if let Some(optional_params) = optional_params {
-            let mut url = format!(
-                "{}{}/posts{}?api_key={}",
-                BLOG,
-                blog_identifier,
-                if let Some(r#type) = optional_params.r#type { format!("/{}", r#type) } else { String::new() },
-                self.keys.consumer_key,
-            );
-            build_url!(
-                url,
-                [
-                    ("type", optional_params.r#type),
-                    ("id", optional_params.id),
-                    ("tag", optional_params.tag),
-                    ("limit", optional_params.limit),
-                    ("offset", optional_params.offset),
-                    ("reblog_info", optional_params.reblog_info),
-                    ("notes_info", optional_params.notes_info),
-                    ("filter", optional_params.filter),
-                    ("before", optional_params.before)
-                ]
-            );
-
-            url
-        } else {
-            format!(
-                "{}{}/posts{}?api_key={}",
-                BLOG,
-                blog_identifier,
-                String::new(),
-                self.keys.consumer_key,
-            )
-        };
+        } else { vec![] }
+    }
 
-        self.get(&url, None)
-            .json()
-            .unwrap()
+    fn validate(&self) -> Result<(), TumblrClientError> { check_limit(self.optional_params.as_ref().and_then(|optional_params| optional_params.limit)) }
+}
+
+pub struct GetBlogPosts {
+    pub blog_identifier: String,
+    pub optional_params: Option<GetBlogPostsOptionalParams>,
+}
+
+impl Endpoint for GetBlogPosts {
+    type Response = Posts;
+
+    fn path(&self) -> String {
+        match self.optional_params.as_ref().and_then(|optional_params| optional_params.r#type) {
+            Some(r#type) => format!("{}{}/posts/{}", BLOG, self.blog_identifier, r#type),
+            None => format!("{}{}/posts", BLOG, self.blog_identifier),
+        }
     }
 
-    pub fn get_blog_posts_queue(&self, blog_identifier: &str, optional_params: Option<GetBlogPostsQueueOptionalParams>) -> Value {
-        let api = format!("{}{}/posts/queue", BLOG, blog_identifier);
-        let params = if let Some(optional_params) = optional_params {
+    fn auth(&self) -> Auth { Auth::ApiKey }
+
+    fn params(&self) -> Vec<(&str, String)> {
+        if let Some(ref optional_params) = self.optional_params {
             set_params![
+                ("type", optional_params.r#type),
+                ("id", optional_params.id),
+                ("tag", optional_params.tag.as_ref()),
                 ("limit", optional_params.limit),
                 ("offset", optional_params.offset),
-                ("filter", optional_params.filter)
+                ("reblog_info", optional_params.reblog_info),
+                ("notes_info", optional_params.notes_info),
+                ("filter", optional_params.filter),
+                ("before", optional_params.before.as_ref().map(timestamp)),
+                ("npf", optional_params.npf)
             ]
-        } else { vec![] };
-        let url = build_query(&api, &params);
-        let headers = build_oauth_headers(
-            "GET",
-            &api,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(params)),
-        );
-
-        self.get(&url, Some(headers))
-            .json()
-            .unwrap()
-    }
-
-    pub fn get_blog_posts_draft(&self, blog_identifier: &str, optional_params: Option<GetBlogPostsDraftOptionalParams>) -> Value {
-        let api = format!("{}{}/posts/draft", BLOG, blog_identifier);
-        let params = if let Some(optional_params) = optional_params {
+        } else { vec![] }
+    }
+
+    fn validate(&self) -> Result<(), TumblrClientError> { check_limit(self.optional_params.as_ref().and_then(|optional_params| optional_params.limit)) }
+}
+
+/// `GetBlogPosts` signed as the user rather than with the api key, so that their private posts are listed too.
+struct OwnPosts(GetBlogPosts);
+
+impl Endpoint for OwnPosts {
+    type Response = Posts;
+
+    fn path(&self) -> String { self.0.path() }
+
+    fn params(&self) -> Vec<(&str, String)> { self.0.params() }
+
+    fn validate(&self) -> Result<(), TumblrClientError> { self.0.validate() }
+}
+
+pub struct GetBlogPostsQueue {
+    pub blog_identifier: String,
+    pub optional_params: Option<GetBlogPostsQueueOptionalParams>,
+}
+
+impl Endpoint for GetBlogPostsQueue {
+    type Response = Posts;
+
+    fn path(&self) -> String { format!("{}{}/posts/queue", BLOG, self.blog_identifier) }
+
+    fn params(&self) -> Vec<(&str, String)> {
+        if let Some(ref optional_params) = self.optional_params {
             set_params![
-               ("before_id", optional_params.before_id),
-               ("filter", optional_params.filter)
-          ]
-        } else { vec![] };
-        let url = build_query(&api, &params);
-        let headers = build_oauth_headers(
-            "GET",
-            &api,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(params)),
-        );
-
-        self.get(&url, Some(headers))
-            .json()
-            .unwrap()
-    }
-
-    pub fn get_blog_posts_submission(&self, blog_identifier: &str, optional_params: Option<GetBlogPostsSubmissionOptionalParams>) -> Value {
-        let api = format!("{}{}/posts/submission", BLOG, blog_identifier);
-        let params = if let Some(optional_params) = optional_params {
+                ("limit", optional_params.limit),
+                ("offset", optional_params.offset),
+                ("filter", optional_params.filter),
+                ("npf", optional_params.npf)
+            ]
+        } else { vec![] }
+    }
+
+    fn validate(&self) -> Result<(), TumblrClientError> { check_limit(self.optional_params.as_ref().and_then(|optional_params| optional_params.limit)) }
+}
+
+pub struct GetBlogPostsDraft {
+    pub blog_identifier: String,
+    pub optional_params: Option<GetBlogPostsDraftOptionalParams>,
+}
+
+impl Endpoint for GetBlogPostsDraft {
+    type Response = Posts;
+
+    fn path(&self) -> String { format!("{}{}/posts/draft", BLOG, self.blog_identifier) }
+
+    fn params(&self) -> Vec<(&str, String)> {
+        if let Some(ref optional_params) = self.optional_params {
+            set_params![
+                ("before_id", optional_params.before_id),
+                ("filter", optional_params.filter),
+                ("npf", optional_params.npf)
+            ]
+        } else { vec![] }
+    }
+}
+
+pub struct GetBlogPostsSubmission {
+    pub blog_identifier: String,
+    pub optional_params: Option<GetBlogPostsSubmissionOptionalParams>,
+}
+
+impl Endpoint for GetBlogPostsSubmission {
+    type Response = Posts;
+
+    fn path(&self) -> String { format!("{}{}/posts/submission", BLOG, self.blog_identifier) }
+
+    fn params(&self) -> Vec<(&str, String)> {
+        if let Some(ref optional_params) = self.optional_params {
             set_params![
                 ("offset", optional_params.offset),
                 ("filter", optional_params.filter)
             ]
-        } else { vec![] };
-        let url = build_query(&api, &params);
-        let headers = build_oauth_headers(
-            "GET",
-            &api,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(params)),
-        );
+        } else { vec![] }
+    }
+}
 
-        self.get(&url, Some(headers))
-            .json()
-            .unwrap()
+/// A legacy post, edit or reblog. `TumblrClient::legacy_post` also applies the write policy to it.
+pub struct LegacyPost {
+    pub blog_identifier: String,
+    pub action: PostAction,
+    pub post_type: PostType,
+    pub optional_params: Option<LegacyPostOptionalParams>,
+}
+
+impl LegacyPost {
+    fn creates(&self) -> bool { !matches!(self.action, PostAction::Edit(_)) }
+
+    /// The field `PostMatch::BodyHash` recognizes the post by.
+    fn fingerprint(&self) -> Option<(&'static str, &str)> {
+        // --- custom ---
+        use self::PostType::*;
+
+        match &self.post_type {
+            Text { body, .. } => Some(("body", body)),
+            Quote { quote, .. } => Some(("text", quote)),
+            Link { url, .. } => Some(("url", url)),
+            Chat { conversation, .. } => Some(("body", conversation)),
+            Photo { caption, .. } | Audio { caption, .. } | Video { caption, .. } => caption.as_ref().map(|caption| ("caption", caption)),
+        }
+        .map(|(field, text)| (field, text.as_str()))
     }
+}
 
-    pub fn legacy_post(&self, blog_identifier: &str, posts_action: PostAction, posts_type: PostType, optional_params: Option<LegacyPostOptionalParams>) -> Value {
+impl Endpoint for LegacyPost {
+    type Response = PostId;
+
+    fn method(&self) -> Method { Method::POST }
+
+    fn path(&self) -> String {
+        match self.action {
+            PostAction::New => format!("{}{}/post", BLOG, self.blog_identifier),
+            PostAction::Edit(_) => format!("{}{}/post/edit", BLOG, self.blog_identifier),
+            PostAction::Reblog { .. } => format!("{}{}/post/reblog", BLOG, self.blog_identifier),
+        }
+    }
+
+    fn params(&self) -> Vec<(&str, String)> {
         // --- custom ---
         use self::{
             PostAction::*,
             PostType::*,
         };
 
-        let api;
-        let params = {
-            let mut v = if let Some(optional_params) = optional_params {
-                set_params![
-                    ("state", optional_params.state),
-                    ("tags", optional_params.tags),
-                    ("tweet", optional_params.tweet),
-                    ("date", optional_params.date),
-                    ("format", optional_params.format),
-                    ("slug", optional_params.slug),
-                    ("native_inline_images", optional_params.native_inline_images)
-                ]
-            } else { vec![] };
-
-            match posts_action {
-                New => {
-                    api = format!("{}{}/post", BLOG, blog_identifier);
-                }
-                Edit(id) => {
-                    api = format!("{}{}/post/edit", BLOG, blog_identifier);
-                    v.push(("id", id));
-                }
-                Reblog {
-                    id,
-                    reblog_key,
-                    comment,
-                    native_inline_images,
-                } => {
-                    api = format!("{}{}/post/reblog", BLOG, blog_identifier);
-                    v.push(("id", id));
-                    v.push(("reblog_key", reblog_key));
-                    v.push(("comment", comment));
-                    v.push(("native_inline_images", native_inline_images));
-                }
+        let mut v = if let Some(ref optional_params) = self.optional_params {
+            set_params![
+                ("state", optional_params.state),
+                ("tags", optional_params.tags.as_deref().map(join_tags)),
+                ("tweet", optional_params.tweet.as_ref()),
+                ("date", optional_params.date.as_ref().map(gmt)),
+                ("format", optional_params.format),
+                ("slug", optional_params.slug.as_ref()),
+                ("native_inline_images", optional_params.native_inline_images)
+            ]
+        } else { vec![] };
+        let mut push = |k, value: &str| v.push((k, value.to_owned()));
+
+        match &self.action {
+            New => (),
+            Edit(id) => push("id", id),
+            Reblog {
+                id,
+                reblog_key,
+                comment,
+                native_inline_images,
+            } => {
+                push("id", id);
+                push("reblog_key", reblog_key);
+                push("comment", comment);
+                push("native_inline_images", &native_inline_images.to_string());
             }
+        }
 
-            match posts_type {
-                Text { title, body } => {
-                    v.push(("type", "text"));
-                    v.push(("body", body));
-                    if let Some(title) = title { v.push(("title", title)); }
-                }
-                Photo { caption, link, source, data, data64 } => {
-                    v.push(("type", "photo"));
-                    if let Some(caption) = caption { v.push(("caption", caption)); }
-                    if let Some(link) = link { v.push(("link", link)); }
-                    if let Some(source) = source { v.push(("source", source)); } else {
-                        if let Some(data64) = data64 { v.push(("data64", data64)); } else {
-                            if let Some(data) = data {
-                                loop {
-                                    let headers = build_oauth_headers(
-                                        "POST",
-                                        &api,
-                                        &self.keys.consumer(),
-                                        Some(&self.keys.token()),
-                                        Some(&build_params(v.clone())),
-                                    );
-
-                                    let mut form = Form::new();
-                                    for (k, v) in v.iter().cloned() { form = form.text(k.to_owned(), v.to_owned()); }
-                                    for (i, f) in data.iter().enumerate() { form = form.file(format!("data[{}]", i), f.to_owned()).unwrap(); }
-
-                                    match self.session.post(&api)
-                                        .headers(headers)
-                                        .multipart(form)
-                                        .send() {
-                                        Ok(mut resp) => return resp.json().unwrap(),
-                                        Err(e) => {
-                                            println!("{:?}", e);
-                                            continue;
-                                        }
-                                    }
-                                }
-                            } else {
-                                panic!("one of [source, data, data64] must be specify")
-                            }
-                        }
-                    }
-                }
-                Quote { quote, source } => {
-                    v.push(("type", "quote"));
-                    v.push(("quote", quote));
-                    if let Some(source) = source { v.push(("source", source)); }
-                }
-                Link { title, url, description, thumbnail, excerpt, author } => {
-                    v.push(("type", "link"));
-                    v.push(("url", url));
-                    if let Some(title) = title { v.push(("title", title)); }
-                    if let Some(description) = description { v.push(("description", description)); }
-                    if let Some(thumbnail) = thumbnail { v.push(("thumbnail", thumbnail)); }
-                    if let Some(excerpt) = excerpt { v.push(("excerpt", excerpt)); }
-                    if let Some(author) = author { v.push(("author", author)); }
-                }
-                Chat { title, conversation } => {
-                    v.push(("type", "chat"));
-                    v.push(("conversation", conversation));
-                    if let Some(title) = title { v.push(("title", title)); }
-                }
-                Audio { caption, external_url, data } => {
-                    v.push(("type", "audio"));
-                    if let Some(caption) = caption { v.push(("caption", caption)); }
-                    if let Some(external_url) = external_url { v.push(("external_url", external_url)); } else {
-                        if let Some(data) = data { v.push(("data", data)); } else {
-                            panic!("one of [external_url, data] must be specify")
-                        }
-                    }
-                }
-                Video { caption, embed, data } => {
-                    v.push(("type", "video"));
-                    if let Some(caption) = caption { v.push(("caption", caption)); }
-                    if let Some(embed) = embed { v.push(("embed", embed)); } else {
-                        if let Some(data) = data { v.push(("data", data)); } else {
-                            panic!("one of [embed, data] must be specify")
-                        }
+        match &self.post_type {
+            Text { title, body } => {
+                push("type", "text");
+                push("body", body);
+                if let Some(title) = title { push("title", title); }
+            }
+            Photo { caption, link, source, data64, .. } => {
+                push("type", "photo");
+                if let Some(caption) = caption { push("caption", caption); }
+                if let Some(link) = link { push("link", link); }
+                if let Some(source) = source { push("source", source); } else if let Some(data64) = data64 { push("data64", data64); }
+            }
+            Quote { quote, source } => {
+                push("type", "quote");
+                push("quote", quote);
+                if let Some(source) = source { push("source", source); }
+            }
+            Link { title, url, description, thumbnail, excerpt, author } => {
+                push("type", "link");
+                push("url", url);
+                if let Some(title) = title { push("title", title); }
+                if let Some(description) = description { push("description", description); }
+                if let Some(thumbnail) = thumbnail { push("thumbnail", thumbnail); }
+                if let Some(excerpt) = excerpt { push("excerpt", excerpt); }
+                if let Some(author) = author { push("author", author); }
+            }
+            Chat { title, conversation } => {
+                push("type", "chat");
+                push("conversation", conversation);
+                if let Some(title) = title { push("title", title); }
+            }
+            Audio { caption, external_url, data } => {
+                push("type", "audio");
+                if let Some(caption) = caption { push("caption", caption); }
+                if let Some(external_url) = external_url { push("external_url", external_url); } else if let Some(data) = data { push("data", data); }
+            }
+            Video { caption, embed, data } => {
+                push("type", "video");
+                if let Some(caption) = caption { push("caption", caption); }
+                if let Some(embed) = embed { push("embed", embed); } else if let Some(data) = data { push("data", data); }
+            }
+        }
+
+        v
+    }
+
+    fn validate(&self) -> Result<(), TumblrClientError> {
+        check_tags(self.optional_params.as_ref().and_then(|optional_params| optional_params.tags.as_deref()))?;
+
+        let msg = match self.post_type {
+            PostType::Photo { source: None, data: None, data64: None, .. } => "one of [source, data, data64] must be specify",
+            PostType::Audio { external_url: None, data: None, .. } => "one of [external_url, data] must be specify",
+            PostType::Video { embed: None, data: None, .. } => "one of [embed, data] must be specify",
+            _ => return Ok(()),
+        };
+
+        Err(TumblrClientError::InvalidInput { msg: msg.to_owned() })
+    }
+
+    /// Photos uploaded as files make a multipart body.
+    fn body(self) -> Result<RequestBody, TumblrClientError> {
+        Ok(match self.post_type {
+            PostType::Photo { source: None, data64: None, data: Some(files), .. } => RequestBody::Multipart(files.iter()
+                .enumerate()
+                .map(|(i, f)| (format!("data[{}]", i), FormField::File((*f).to_owned())))
+                .collect()),
+            _ => RequestBody::Empty,
+        })
+    }
+}
+
+/// An NPF post, edit or reblog. `TumblrClient::create_npf_post` and the like also apply the write policy to it.
+pub struct NpfPost {
+    pub blog_identifier: String,
+    pub action: NpfAction,
+    pub content: Vec<NpfContent>,
+    pub optional_params: Option<NpfPostOptionalParams>,
+}
+
+impl Endpoint for NpfPost {
+    type Response = PostId;
+
+    fn method(&self) -> Method { if let NpfAction::Edit(_) = self.action { Method::PUT } else { Method::POST } }
+
+    fn path(&self) -> String {
+        match &self.action {
+            NpfAction::Edit(post_id) => format!("{}{}/posts/{}", BLOG, self.blog_identifier, post_id),
+            NpfAction::New | NpfAction::Reblog(_) => format!("{}{}/posts", BLOG, self.blog_identifier),
+        }
+    }
+
+    fn validate(&self) -> Result<(), TumblrClientError> {
+        if let Some(ref optional_params) = self.optional_params { optional_params.validate()?; }
+        for content in &self.content {
+            match content {
+                NpfContent::Upload(ContentBlock::Image(_), _) | NpfContent::Upload(ContentBlock::Audio(_), _) | NpfContent::Upload(ContentBlock::Video(_), _) | NpfContent::Block(_) => (),
+                NpfContent::Upload(..) => return Err(TumblrClientError::InvalidInput { msg: "only image, audio and video blocks can be uploaded".to_owned() }),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The post as JSON, with its uploads after it in a multipart body if there are any.
+    fn body(self) -> Result<RequestBody, TumblrClientError> {
+        // --- external ---
+        use serde_json::Map;
+
+        let optional_params = self.optional_params.unwrap_or_default();
+        let mut blocks = vec![];
+        let mut fields = vec![];
+        for content in self.content {
+            match content {
+                NpfContent::Block(block) => blocks.push(block),
+                NpfContent::Upload(mut block, media) => {
+                    let identifier = format!("media-{}", fields.len());
+                    let media_object = media.media_object(&identifier);
+                    match block {
+                        ContentBlock::Image(ref mut image) => image.media = vec![media_object],
+                        ContentBlock::Audio(ref mut audio) => audio.media = Some(media_object),
+                        ContentBlock::Video(ref mut video) => video.media = Some(media_object),
+                        _ => (),
                     }
+
+                    fields.push((identifier, FormField::Media(media)));
+                    blocks.push(block);
                 }
             }
+        }
+
+        let mut body = Map::new();
+        body.insert("content".to_owned(), serde_json::to_value(blocks)?);
+        if let Some(ref layout) = optional_params.layout { body.insert("layout".to_owned(), serde_json::to_value(layout)?); }
+        for (k, v) in optional_params.params() { body.insert(k.to_owned(), Value::from(v)); }
+        if let Some(send_to_twitter) = optional_params.send_to_twitter { body.insert("send_to_twitter".to_owned(), Value::from(send_to_twitter)); }
+        if let Some(is_private) = optional_params.is_private { body.insert("is_private".to_owned(), Value::from(is_private)); }
+        if let NpfAction::Reblog(parent) = self.action {
+            body.insert("parent_tumblelog_uuid".to_owned(), Value::from(parent.parent_tumblelog_uuid));
+            body.insert("parent_post_id".to_owned(), Value::from(parent.parent_post_id));
+            body.insert("reblog_key".to_owned(), Value::from(parent.reblog_key));
+        }
+
+        Ok(if fields.is_empty() { RequestBody::Json(Value::from(body)) } else {
+            fields.insert(0, ("json".to_owned(), FormField::Json(Value::from(body))));
+            RequestBody::Multipart(fields)
+        })
+    }
+}
+
+pub struct DeletePost {
+    pub blog_identifier: String,
+    pub id: String,
+}
+
+impl Endpoint for DeletePost {
+    type Response = PostId;
+
+    fn method(&self) -> Method { Method::POST }
 
-            v
+    fn path(&self) -> String { format!("{}{}/post/delete", BLOG, self.blog_identifier) }
+
+    fn params(&self) -> Vec<(&str, String)> { vec![("id", self.id.to_owned())] }
+}
+
+impl TumblrClient {
+    pub fn get_blog_info(&self, blog_identifier: &str) -> Result<BlogInfo, TumblrClientError> { self.call(GetBlogInfo { blog_identifier: blog_identifier.to_owned() }) }
+
+    pub fn get_blog_avatar(&self, blog_identifier: &str, optional_params: Option<GetBlogAvatarOptionalParams>) -> Result<Vec<u8>, TumblrClientError> {
+        let mut resp = self.call_raw(GetBlogAvatar { blog_identifier: blog_identifier.to_owned(), optional_params })?;
+        if !resp.status().is_success() { return parse_json(resp).map(|_| vec![]); }
+
+        let mut bytes = vec![];
+        resp.copy_to(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    pub fn get_blog_likes(&self, blog_identifier: &str, optional_params: Option<GetBlogLikesOptionalParams>) -> Result<Likes, TumblrClientError> {
+        self.call(GetBlogLikes { blog_identifier: blog_identifier.to_owned(), optional_params })
+    }
+
+    pub fn get_blog_following(&self, blog_identifier: &str, optional_params: Option<GetBlogFollowingOptionalParams>) -> Result<Following, TumblrClientError> {
+        self.call(GetBlogFollowing { blog_identifier: blog_identifier.to_owned(), optional_params })
+    }
+
+    pub fn get_blog_followers(&self, blog_identifier: &str, optional_params: Option<GetBlogFollowersOptionalParams>) -> Result<Followers, TumblrClientError> {
+        self.call(GetBlogFollowers { blog_identifier: blog_identifier.to_owned(), optional_params })
+    }
+
+    pub fn get_blog_posts(&self, blog_identifier: &str, optional_params: Option<GetBlogPostsOptionalParams>) -> Result<Posts, TumblrClientError> {
+        self.call(GetBlogPosts { blog_identifier: blog_identifier.to_owned(), optional_params })
+    }
+
+    pub fn get_blog_posts_queue(&self, blog_identifier: &str, optional_params: Option<GetBlogPostsQueueOptionalParams>) -> Result<Posts, TumblrClientError> {
+        self.call(GetBlogPostsQueue { blog_identifier: blog_identifier.to_owned(), optional_params })
+    }
+
+    pub fn get_blog_posts_draft(&self, blog_identifier: &str, optional_params: Option<GetBlogPostsDraftOptionalParams>) -> Result<Posts, TumblrClientError> {
+        self.call(GetBlogPostsDraft { blog_identifier: blog_identifier.to_owned(), optional_params })
+    }
+
+    pub fn get_blog_posts_submission(&self, blog_identifier: &str, optional_params: Option<GetBlogPostsSubmissionOptionalParams>) -> Result<Posts, TumblrClientError> {
+        self.call(GetBlogPostsSubmission { blog_identifier: blog_identifier.to_owned(), optional_params })
+    }
+
+    pub fn legacy_post(&self, blog_identifier: &str, posts_action: PostAction, posts_type: PostType, optional_params: Option<LegacyPostOptionalParams>) -> Result<PostId, TumblrClientError> {
+        self.verified_post(blog_identifier, self.legacy_post_request(blog_identifier, posts_action, posts_type, optional_params)?)
+    }
+
+    pub(super) fn legacy_post_request(&self, blog_identifier: &str, posts_action: PostAction, posts_type: PostType, optional_params: Option<LegacyPostOptionalParams>) -> Result<(Request, Option<PostLookup>), TumblrClientError> {
+        let post_match = self.post_match();
+        let optional_params = match post_match {
+            Some(PostMatch::Marker(marker)) => Some(optional_params.unwrap_or_default().tag(marker)),
+            _ => optional_params,
+        };
+        let post = LegacyPost {
+            blog_identifier: blog_identifier.to_owned(),
+            action: posts_action,
+            post_type: posts_type,
+            optional_params,
+        };
+        post.validate()?;
+
+        let lookup = match post_match {
+            Some(post_match) if post.creates() => Some(PostLookup::new(post_match, &post.params(), post.fingerprint().map(|(field, body)| Fingerprint::Field(field, body)))?),
+            _ => None,
         };
 
-        let headers = build_oauth_headers(
-            "POST",
-            &api,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(params.clone())),
-        );
-
-        self.post(&api, headers, &params)
-            .json()
-            .unwrap()
-    }
-
-//    pub fn neue_post(
-//        &self,
-//        blog_identifier: &str,
-//    ) -> Value {
-//        unimplemented!()
-//    }
-
-    pub fn delete_post(&self, blog_identifier: &str, id: &str) -> Value {
-        let api = format!("{}{}/post/delete", BLOG, blog_identifier);
-        let form = vec![("id", id)];
-        let headers = build_oauth_headers(
-            "POST",
-            &api,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(form.clone())),
-        );
-
-        self.post(&api, headers, &form)
-            .json()
-            .unwrap()
+        Ok((self.endpoint_request(post)?, lookup))
+    }
+
+    fn find_post(&self, blog_identifier: &str, lookup: &PostLookup) -> Result<Option<Value>, TumblrClientError> {
+        let request = match lookup.state.as_deref() {
+            Some("draft") => self.endpoint_request(GetBlogPostsDraft { blog_identifier: blog_identifier.to_owned(), optional_params: Some(GetBlogPostsDraftOptionalParams::new().npf(lookup.npf)) })?,
+            Some("queue") => self.endpoint_request(GetBlogPostsQueue { blog_identifier: blog_identifier.to_owned(), optional_params: Some(GetBlogPostsQueueOptionalParams::new().limit(20).npf(lookup.npf)) })?,
+            state => {
+                let posts = GetBlogPosts { blog_identifier: blog_identifier.to_owned(), optional_params: Some(GetBlogPostsOptionalParams::new().limit(20).npf(lookup.npf)) };
+                if state == Some("private") { self.endpoint_request(OwnPosts(posts))? } else { self.endpoint_request(posts)? }
+            }
+        };
+        let resp = parse_json(self.send(&request)?)?;
+
+        Ok(resp["response"]["posts"].as_array()
+            .and_then(|posts| posts.iter().find(|post| lookup.matches(post)))
+            .map(|post| serde_json::json!({
+                "meta": { "status": 201, "msg": "Created" },
+                "response": { "id": post["id"] },
+            })))
+    }
+
+    pub fn create_npf_post(&self, blog_identifier: &str, content: &[ContentBlock], optional_params: Option<NpfPostOptionalParams>) -> Result<PostId, TumblrClientError> {
+        self.create_npf_post_with_media(blog_identifier, NpfContent::blocks(content), optional_params)
+    }
+
+    pub fn create_npf_post_with_media(&self, blog_identifier: &str, content: Vec<NpfContent>, optional_params: Option<NpfPostOptionalParams>) -> Result<PostId, TumblrClientError> {
+        self.verified_post(blog_identifier, self.create_npf_post_request(blog_identifier, content, optional_params)?)
+    }
+
+    pub(super) fn create_npf_post_request(&self, blog_identifier: &str, content: Vec<NpfContent>, optional_params: Option<NpfPostOptionalParams>) -> Result<(Request, Option<PostLookup>), TumblrClientError> {
+        self.npf_post_request(blog_identifier, NpfAction::New, content, optional_params)
+    }
+
+    pub fn edit_npf_post(&self, blog_identifier: &str, post_id: &str, content: &[ContentBlock], optional_params: Option<NpfPostOptionalParams>) -> Result<PostId, TumblrClientError> {
+        self.edit_npf_post_with_media(blog_identifier, post_id, NpfContent::blocks(content), optional_params)
+    }
+
+    pub fn edit_npf_post_with_media(&self, blog_identifier: &str, post_id: &str, content: Vec<NpfContent>, optional_params: Option<NpfPostOptionalParams>) -> Result<PostId, TumblrClientError> {
+        self.verified_post(blog_identifier, self.edit_npf_post_request(blog_identifier, post_id, content, optional_params)?)
+    }
+
+    pub(super) fn edit_npf_post_request(&self, blog_identifier: &str, post_id: &str, content: Vec<NpfContent>, optional_params: Option<NpfPostOptionalParams>) -> Result<(Request, Option<PostLookup>), TumblrClientError> {
+        self.npf_post_request(blog_identifier, NpfAction::Edit(post_id.to_owned()), content, optional_params)
+    }
+
+    /// Reblog `parent`, with `content` as the reblog's own blocks.
+    pub fn reblog_npf_post(&self, blog_identifier: &str, parent: NpfReblog, content: &[ContentBlock], optional_params: Option<NpfPostOptionalParams>) -> Result<PostId, TumblrClientError> {
+        self.reblog_npf_post_with_media(blog_identifier, parent, NpfContent::blocks(content), optional_params)
+    }
+
+    pub fn reblog_npf_post_with_media(&self, blog_identifier: &str, parent: NpfReblog, content: Vec<NpfContent>, optional_params: Option<NpfPostOptionalParams>) -> Result<PostId, TumblrClientError> {
+        self.verified_post(blog_identifier, self.reblog_npf_post_request(blog_identifier, parent, content, optional_params)?)
+    }
+
+    pub(super) fn reblog_npf_post_request(&self, blog_identifier: &str, parent: NpfReblog, content: Vec<NpfContent>, optional_params: Option<NpfPostOptionalParams>) -> Result<(Request, Option<PostLookup>), TumblrClientError> {
+        self.npf_post_request(blog_identifier, NpfAction::Reblog(parent), content, optional_params)
+    }
+
+    fn npf_post_request(&self, blog_identifier: &str, action: NpfAction, content: Vec<NpfContent>, optional_params: Option<NpfPostOptionalParams>) -> Result<(Request, Option<PostLookup>), TumblrClientError> {
+        let post_match = self.post_match();
+        let optional_params = match post_match {
+            Some(PostMatch::Marker(marker)) => optional_params.unwrap_or_default().tag(marker),
+            _ => optional_params.unwrap_or_default(),
+        };
+        let lookup = match post_match {
+            Some(post_match) if !matches!(action, NpfAction::Edit(_)) => {
+                let text = text_of(content.iter().filter_map(|content| match content {
+                    NpfContent::Block(ContentBlock::Text(text)) => Some(text.text.as_str()),
+                    _ => None,
+                }));
+
+                Some(PostLookup::new(post_match, &optional_params.params(), Some(Fingerprint::Text(text)))?)
+            }
+            _ => None,
+        };
+        let post = NpfPost {
+            blog_identifier: blog_identifier.to_owned(),
+            action,
+            content,
+            optional_params: Some(optional_params),
+        };
+
+        Ok((self.endpoint_request(post)?, lookup))
+    }
+
+    fn post_match(&self) -> Option<&PostMatch> { if let WritePolicy::Verify(ref post_match) = self.write_policy { Some(post_match) } else { None } }
+
+    /// Send a post, looking for it before resending if the write policy verifies writes.
+    fn verified_post(&self, blog_identifier: &str, request: (Request, Option<PostLookup>)) -> Result<PostId, TumblrClientError> {
+        response(match request {
+            (request, Some(lookup)) => self.create(&request, || self.find_post(blog_identifier, &lookup))?,
+            (request, None) => parse_json(self.send(&request)?)?,
+        })
+    }
+
+    pub fn delete_post(&self, blog_identifier: &str, id: &str) -> Result<PostId, TumblrClientError> { self.call(DeletePost { blog_identifier: blog_identifier.to_owned(), id: id.to_owned() }) }
+}
+
+#[cfg(test)]
+mod tests {
+    // --- external ---
+    use serde_json::json;
+    // --- custom ---
+    use super::*;
+
+    /// Parse `json` as a `T`, check it with `parsed`, and serialize it back unchanged.
+    fn round_trip<T: Serialize + for<'de> Deserialize<'de>>(json: Value, parsed: impl Fn(&T) -> bool) {
+        let value = serde_json::from_value::<T>(json.clone()).unwrap();
+        assert!(parsed(&value), "{}", json);
+        assert_eq!(serde_json::to_value(&value).unwrap(), json);
+    }
+
+    #[test]
+    fn blocks_round_trip() {
+        round_trip(json!({
+            "type": "text",
+            "text": "Hello world",
+            "subtype": "heading1",
+            "formatting": [{ "start": 0, "end": 5, "type": "bold" }, { "start": 6, "end": 11, "type": "color", "hex": "#ff492f" }],
+            "unmodeled": true,
+        }), |block| matches!(block, ContentBlock::Text(text) if text.subtype == Some(TextSubtype::Heading1) && text.extra["unmodeled"] == true));
+        round_trip(json!({
+            "type": "image",
+            "media": [{ "url": "https://64.media.tumblr.com/a.jpg", "type": "image/jpeg", "width": 540, "height": 405 }],
+            "alt_text": "A cat",
+            "attribution": { "type": "link", "url": "https://example.com" },
+            "exif": { "Camera": "Z" },
+        }), |block| matches!(block, ContentBlock::Image(image) if image.media[0].width == Some(540) && image.extra.contains_key("exif")));
+        round_trip(json!({
+            "type": "link",
+            "url": "https://example.com",
+            "title": "Example",
+            "poster": [{ "url": "https://64.media.tumblr.com/b.jpg", "width": 100, "height": 100 }],
+            "unmodeled": 1,
+        }), |block| matches!(block, ContentBlock::Link(link) if link.title.as_deref() == Some("Example") && link.extra.len() == 1));
+        round_trip(json!({
+            "type": "audio",
+            "provider": "tumblr",
+            "media": { "url": "https://a.tumblr.com/c.mp3", "type": "audio/mpeg" },
+            "artist": "Someone",
+            "unmodeled": "kept",
+        }), |block| matches!(block, ContentBlock::Audio(audio) if audio.artist.as_deref() == Some("Someone") && audio.extra.len() == 1));
+        round_trip(json!({
+            "type": "video",
+            "provider": "youtube",
+            "url": "https://www.youtube.com/watch?v=x",
+            "embed_iframe": { "url": "https://www.youtube.com/embed/x", "width": 540, "height": 304 },
+            "can_autoplay_on_cellular": false,
+            "unmodeled": [],
+        }), |block| matches!(block, ContentBlock::Video(video) if video.embed_iframe.as_ref().is_some_and(|iframe| iframe.height == 304) && video.extra.len() == 1));
+        round_trip(json!({
+            "type": "paywall",
+            "subtype": "cta",
+            "url": "https://www.tumblr.com/support",
+            "title": "Support me",
+            "is_visible": true,
+        }), |block| matches!(block, ContentBlock::Paywall(paywall) if paywall.subtype == "cta" && paywall.extra["is_visible"] == true));
+        round_trip(json!({
+            "type": "poll",
+            "client_id": "c",
+            "question": "Cats or dogs?",
+            "answers": [{ "client_id": "a", "answer_text": "Cats" }, { "client_id": "b", "answer_text": "Dogs" }],
+            "settings": { "multiple_choice": true, "close_status": "closed-after", "expire_after": 604800, "source": "tumblr", "unmodeled": 0 },
+            "timestamp": 1_600_000_000,
+            "votes": 2,
+        }), |block| matches!(block, ContentBlock::Poll(poll) if poll.settings.multiple_choice && poll.settings.source.as_deref() == Some("tumblr") && poll.extra["votes"] == 2));
+    }
+
+    #[test]
+    fn layouts_round_trip() {
+        round_trip(json!({
+            "type": "rows",
+            "display": [{ "blocks": [0, 1], "mode": { "type": "weighted" } }, { "blocks": [2] }],
+            "truncate_after": 1,
+            "unmodeled": true,
+        }), |layout| matches!(layout, Layout::Rows(rows) if rows.display.len() == 2 && rows.extra.len() == 1));
+        round_trip(json!({ "type": "condensed", "blocks": [0, 1], "unmodeled": true }), |layout| matches!(layout, Layout::Condensed(condensed) if condensed.blocks == [0, 1] && condensed.extra.len() == 1));
+        round_trip(json!({
+            "type": "ask",
+            "blocks": [0],
+            "attribution": { "type": "blog", "blog": { "uuid": "t:x", "name": "asker" } },
+            "unmodeled": true,
+        }), |layout| matches!(layout, Layout::Ask(ask) if ask.attribution.is_some() && ask.extra.len() == 1));
+    }
+
+    #[test]
+    fn unknown_round_trip() {
+        round_trip(json!({ "type": "future", "anything": [1, 2] }), |block| matches!(block, ContentBlock::Unknown(_)));
+        // a known type which doesn't match its shape
+        round_trip(json!({ "type": "text", "text": 5 }), |block| matches!(block, ContentBlock::Unknown(_)));
+        round_trip(json!({ "type": "masonry", "columns": 3 }), |layout| matches!(layout, Layout::Unknown(_)));
     }
 }
@@ -0,0 +1,262 @@
+// --- std ---
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+// --- external ---
+use reqwest::{Response, Url, header::HeaderMap};
+use serde_json::Value;
+// --- custom ---
+use super::{
+    TumblrClient, TumblrClientError,
+    request::{Body, PartData, Request},
+};
+
+/// Parameters and response fields carrying credentials, never written to a cassette.
+const SECRETS: &[&str] = &[
+    "api_key",
+    "client_id",
+    "client_secret",
+    "code",
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "oauth_token",
+    "oauth_token_secret",
+    "oauth_verifier",
+];
+const REDACTED: &str = "REDACTED";
+/// Stands for the content of a file or bytes part of a multipart body.
+const BINARY: &str = "BINARY";
+/// Response headers describing the body as sent over the wire rather than as recorded, or carrying credentials.
+const SKIPPED_HEADERS: &[&str] = &["content-length", "content-encoding", "transfer-encoding", "set-cookie"];
+
+/// Request and response pairs recorded by `TumblrClient::record_cassette`, in the order they were sent.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette { pub interactions: Vec<Interaction> }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: CassetteRequest,
+    pub response: CassetteResponse,
+}
+
+/// What a request is matched on: no headers, so no OAuth nonce, timestamp or signature.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CassetteRequest {
+    pub method: String,
+    /// Without the host, so a cassette recorded against Tumblr replays under any base url.
+    pub path: String,
+    /// The query and form parameters, sorted, with credentials redacted.
+    pub params: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json: Option<Value>,
+}
+
+/// The body is kept as `json` if it parses, otherwise as `text`, otherwise as `base64`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base64: Option<String>,
+}
+
+impl Cassette {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Cassette, TumblrClientError> { Ok(serde_json::from_reader(File::open(path)?)?) }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), TumblrClientError> {
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+
+        Ok(())
+    }
+}
+
+impl CassetteRequest {
+    pub(super) fn new(request: &Request) -> CassetteRequest {
+        let url = Url::parse(&request.url).ok();
+        let mut params = url.as_ref()
+            .map(|url| url.query_pairs().into_owned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let mut json = None;
+        match request.body {
+            Body::Empty => (),
+            Body::Form(ref form) => params.extend(form.iter().cloned()),
+            Body::Json(ref body) => json = serde_json::from_str(body).ok().map(redact_json),
+            Body::Multipart(ref parts) => params.extend(parts.iter().map(|part| (part.name.clone(), match part.data {
+                PartData::Text(ref text) => text.clone(),
+                _ => BINARY.to_owned(),
+            }))),
+        }
+        redact_params(&mut params);
+        params.sort();
+
+        CassetteRequest {
+            method: request.method.as_str().to_owned(),
+            path: url.map_or_else(|| request.url.clone(), |url| url.path().to_owned()),
+            params,
+            json,
+        }
+    }
+}
+
+impl CassetteResponse {
+    fn new(status: u16, headers: &HeaderMap, body: Vec<u8>) -> CassetteResponse {
+        let mut response = CassetteResponse {
+            status,
+            headers: headers.iter()
+                .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.as_str()))
+                .map(|(name, value)| (name.as_str().to_owned(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+                .collect(),
+            json: None,
+            text: None,
+            base64: None,
+        };
+        match String::from_utf8(body) {
+            Ok(text) => match serde_json::from_str(&text) {
+                Ok(json) => response.json = Some(redact_json(json)),
+                Err(_) => response.text = Some(redact_form(text)),
+            },
+            Err(e) => response.base64 = Some(base64::encode(e.into_bytes())),
+        }
+
+        response
+    }
+
+    fn to_response(&self) -> Result<Response, TumblrClientError> {
+        let body = match (&self.json, &self.text, &self.base64) {
+            (Some(json), _, _) => json.to_string().into_bytes(),
+            (_, Some(text), _) => text.clone().into_bytes(),
+            (_, _, Some(base64)) => base64::decode(base64).map_err(|e| TumblrClientError::Cassette { msg: format!("invalid base64 body: {}", e) })?,
+            _ => vec![],
+        };
+
+        build_response(self.status, &self.headers, body)
+    }
+}
+
+/// The cassette a client records into or replays from, shared by its clones.
+#[derive(Debug)]
+pub(super) struct Tape {
+    /// Where every recorded interaction is saved, `None` when replaying.
+    path: Option<PathBuf>,
+    reel: Mutex<Reel>,
+}
+
+#[derive(Debug)]
+struct Reel {
+    cassette: Cassette,
+    played: Vec<bool>,
+}
+
+impl Tape {
+    pub fn replaying(&self) -> bool { self.path.is_none() }
+
+    /// The first recorded response to a request matching `request` which was not replayed yet.
+    pub fn replay(&self, request: &Request) -> Result<Response, TumblrClientError> {
+        let recorded = CassetteRequest::new(request);
+        let mut reel = self.reel.lock().unwrap_or_else(|e| e.into_inner());
+        let Reel { ref cassette, ref mut played } = *reel;
+
+        let i = cassette.interactions
+            .iter()
+            .zip(played.iter())
+            .position(|(interaction, played)| !played && interaction.request == recorded)
+            .ok_or_else(|| TumblrClientError::Cassette { msg: format!("no recorded response left for {} {}", recorded.method, recorded.path) })?;
+        played[i] = true;
+
+        cassette.interactions[i].response.to_response()
+    }
+
+    /// Append `request` and `resp` to the cassette and save it, and hand back an equivalent response.
+    pub fn record(&self, request: &Request, mut resp: Response) -> Result<reqwest::Result<Response>, TumblrClientError> {
+        let mut body = vec![];
+        if let Err(e) = resp.copy_to(&mut body) { return Ok(Err(e)); }
+
+        let response = CassetteResponse::new(resp.status().as_u16(), resp.headers(), body.clone());
+        // the caller gets the credentials the cassette doesn't
+        let resp = build_response(response.status, &response.headers, body)?;
+        let mut reel = self.reel.lock().unwrap_or_else(|e| e.into_inner());
+        reel.cassette.interactions.push(Interaction {
+            request: CassetteRequest::new(request),
+            response,
+        });
+        if let Some(ref path) = self.path { reel.cassette.save(path)?; }
+
+        Ok(Ok(resp))
+    }
+}
+
+impl TumblrClient {
+    /// Record every request and its response into a new cassette at `path`, saved after each response,
+    /// with credentials redacted. Requests failing without a response are not recorded. Blocking client only.
+    pub fn record_cassette<P: AsRef<Path>>(mut self, path: P) -> Result<Self, TumblrClientError> {
+        let cassette = Cassette::default();
+        cassette.save(&path)?;
+        self.tape = Some(Arc::new(Tape {
+            path: Some(path.as_ref().to_owned()),
+            reel: Mutex::new(Reel { cassette, played: vec![] }),
+        }));
+
+        Ok(self)
+    }
+
+    /// Answer every request from the cassette at `path` instead of the network, each recorded response once,
+    /// matching requests on their method, path, params and JSON body. Blocking client only.
+    pub fn replay_cassette<P: AsRef<Path>>(mut self, path: P) -> Result<Self, TumblrClientError> {
+        let cassette = Cassette::load(path)?;
+        self.tape = Some(Arc::new(Tape {
+            path: None,
+            reel: Mutex::new(Reel {
+                played: vec![false; cassette.interactions.len()],
+                cassette,
+            }),
+        }));
+
+        Ok(self)
+    }
+}
+
+fn build_response(status: u16, headers: &[(String, String)], body: Vec<u8>) -> Result<Response, TumblrClientError> {
+    let mut builder = hyper::Response::builder();
+    builder.status(status);
+    for (name, value) in headers { builder.header(name.as_str(), value.as_str()); }
+
+    builder.body(body)
+        .map(Response::from)
+        .map_err(|e| TumblrClientError::Cassette { msg: format!("invalid recorded response: {}", e) })
+}
+
+fn redact_params(params: &mut [(String, String)]) {
+    for (k, v) in params {
+        if SECRETS.contains(&k.as_str()) { *v = REDACTED.to_owned(); }
+    }
+}
+
+fn redact_json(mut json: Value) -> Value {
+    match json {
+        Value::Object(ref mut map) => for (k, v) in map.iter_mut() {
+            *v = if SECRETS.contains(&k.as_str()) && v.is_string() { Value::from(REDACTED) } else { redact_json(v.take()) };
+        },
+        Value::Array(ref mut values) => for v in values.iter_mut() { *v = redact_json(v.take()); },
+        _ => (),
+    }
+
+    json
+}
+
+/// Redact a form encoded body, such as the tokens of the OAuth endpoints.
+fn redact_form(text: String) -> String {
+    let mut params = match serde_urlencoded::from_str::<Vec<(String, String)>>(&text) {
+        Ok(ref params) if params.iter().any(|(k, _)| SECRETS.contains(&k.as_str())) => params.clone(),
+        _ => return text,
+    };
+    redact_params(&mut params);
+
+    serde_urlencoded::to_string(params).unwrap_or(text)
+}
@@ -0,0 +1,241 @@
+// --- std ---
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+// --- external ---
+use toml::value::{Table, Value};
+// --- custom ---
+use super::{Keys, TumblrClient};
+
+/// Profile loaded when none is named and `RUMBLR_PROFILE` is unset.
+const DEFAULT_PROFILE: &str = "default";
+
+impl TumblrClient {
+    pub fn with_keys(consumer_key: &str, consumer_secret: &str, access_key: &str, access_secret: &str) -> TumblrClient {
+        let mut client = TumblrClient::new();
+        client.keys = Keys {
+            consumer_key: consumer_key.to_owned(),
+            consumer_secret: consumer_secret.to_owned(),
+            access_key: access_key.to_owned(),
+            access_secret: access_secret.to_owned(),
+        };
+
+        client
+    }
+
+    /// `$XDG_CONFIG_HOME/rumblr/credentials.toml`, falling back to `~/.config/rumblr/credentials.toml`.
+    pub fn credentials_path() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".config")))
+            .map(|dir| dir.join("rumblr").join("credentials.toml"))
+    }
+
+    /// Load the keys of profile `name` (or `$RUMBLR_PROFILE`, or `default`) from `credentials_path`,
+    /// each of them overridden by its `RUMBLR_*` environment variable, e.g. `RUMBLR_CONSUMER_KEY`.
+    pub fn load_profile(self, name: Option<&str>) -> Result<Self, io::Error> {
+        let path = TumblrClient::credentials_path().ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no home directory"))?;
+
+        self.load_profile_from(path, name)
+    }
+
+    /// Like `load_profile`, from the credentials file at `path`.
+    ///
+    /// A missing file or profile is fine as long as the environment provides the consumer key and secret.
+    pub fn load_profile_from<P: AsRef<Path>>(mut self, path: P, name: Option<&str>) -> Result<Self, io::Error> {
+        let name = profile_name(name);
+        let mut keys = match read_profiles(path.as_ref())?.remove(&name) {
+            Some(profile) => profile.try_into::<Keys>().map_err(|e| invalid_data(format!("profile `{}`: {}", name, e)))?,
+            None => Keys::new(),
+        };
+        override_from_env(&mut keys);
+
+        if keys.consumer_key.is_empty() || keys.consumer_secret.is_empty() {
+            return Err(io::Error::new(ErrorKind::NotFound, format!("no consumer key and secret for profile `{}`", name)));
+        }
+        self.keys = keys;
+
+        Ok(self)
+    }
+
+    /// Override the current keys with the `RUMBLR_*` environment variables which are set.
+    pub fn load_env(mut self) -> Self {
+        override_from_env(&mut self.keys);
+        self
+    }
+
+    /// Save the current keys as profile `name` (or `$RUMBLR_PROFILE`, or `default`) in `credentials_path`,
+    /// keeping the other profiles.
+    pub fn save_profile(&self, name: Option<&str>) -> Result<(), io::Error> {
+        let path = TumblrClient::credentials_path().ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no home directory"))?;
+
+        self.save_profile_to(path, name)
+    }
+
+    /// Like `save_profile`, to the credentials file at `path`.
+    pub fn save_profile_to<P: AsRef<Path>>(&self, path: P, name: Option<&str>) -> Result<(), io::Error> {
+        let path = path.as_ref();
+        let mut profiles = read_profiles(path)?;
+        profiles.insert(profile_name(name), Value::try_from(&self.keys).map_err(invalid_data)?);
+
+        if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+        create_private(path)?.write_all(toml::to_string(&profiles).map_err(invalid_data)?.as_bytes())
+    }
+}
+
+fn profile_name(name: Option<&str>) -> String {
+    match name {
+        Some(name) => name.to_owned(),
+        None => env::var("RUMBLR_PROFILE")
+            .ok()
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_owned()),
+    }
+}
+
+/// Every profile in the credentials file at `path`, none if it does not exist.
+fn read_profiles(path: &Path) -> Result<Table, io::Error> {
+    match fs::read_to_string(path) {
+        Ok(profiles) => toml::from_str(&profiles).map_err(|e| invalid_data(format!("{}: {}", path.display(), e))),
+        Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(Table::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn override_from_env(keys: &mut Keys) {
+    let fields = [
+        ("RUMBLR_CONSUMER_KEY", &mut keys.consumer_key),
+        ("RUMBLR_CONSUMER_SECRET", &mut keys.consumer_secret),
+        ("RUMBLR_ACCESS_KEY", &mut keys.access_key),
+        ("RUMBLR_ACCESS_SECRET", &mut keys.access_secret),
+    ];
+    for (var, field) in fields {
+        if let Some(value) = env::var(var).ok().filter(|value| !value.is_empty()) { *field = value; }
+    }
+}
+
+/// Create or truncate `path`, readable and writable by its owner only since it holds secrets.
+pub(super) fn create_private(path: &Path) -> Result<File, io::Error> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        // --- std ---
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        let file = options.mode(0o600).open(path)?;
+        // `mode` only applies to new files.
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    options.open(path)
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error { io::Error::new(ErrorKind::InvalidData, e) }
+
+#[cfg(test)]
+mod tests {
+    // --- std ---
+    use std::sync::{Mutex, MutexGuard};
+    // --- custom ---
+    use super::*;
+    use crate::client::temp_dir;
+
+    /// Held by every test, as they share the environment.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    const VARS: [&str; 6] = ["RUMBLR_PROFILE", "RUMBLR_CONSUMER_KEY", "RUMBLR_CONSUMER_SECRET", "RUMBLR_ACCESS_KEY", "RUMBLR_ACCESS_SECRET", "XDG_CONFIG_HOME"];
+
+    /// Lock the environment, cleared of the variables read here.
+    fn clean_env() -> MutexGuard<'static, ()> {
+        let lock = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        for var in &VARS { env::remove_var(var); }
+
+        lock
+    }
+
+    fn keys(client: &TumblrClient) -> [&str; 4] {
+        [&client.keys.consumer_key, &client.keys.consumer_secret, &client.keys.access_key, &client.keys.access_secret]
+    }
+
+    #[test]
+    fn profiles() {
+        let _env = clean_env();
+        let dir = temp_dir("profiles");
+        let path = dir.join("rumblr").join("credentials.toml");
+
+        TumblrClient::with_keys("ck", "cs", "ak", "as").save_profile_to(&path, None).unwrap();
+        TumblrClient::with_keys("ck2", "cs2", "", "").save_profile_to(&path, Some("side")).unwrap();
+        assert_eq!(keys(&TumblrClient::new().load_profile_from(&path, None).unwrap()), ["ck", "cs", "ak", "as"]);
+        assert_eq!(keys(&TumblrClient::new().load_profile_from(&path, Some("side")).unwrap()), ["ck2", "cs2", "", ""]);
+
+        // saving again replaces that profile only
+        TumblrClient::with_keys("ck3", "cs3", "", "").save_profile_to(&path, Some("side")).unwrap();
+        assert_eq!(keys(&TumblrClient::new().load_profile_from(&path, Some("side")).unwrap()), ["ck3", "cs3", "", ""]);
+        assert_eq!(keys(&TumblrClient::new().load_profile_from(&path, Some("default")).unwrap()), ["ck", "cs", "ak", "as"]);
+
+        let e = TumblrClient::new().load_profile_from(&path, Some("nobody")).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+        assert!(e.to_string().contains("nobody"), "{}", e);
+
+        fs::write(&path, "not = [toml").unwrap();
+        assert_eq!(TumblrClient::new().load_profile_from(&path, None).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn env_overrides() {
+        let _env = clean_env();
+        let dir = temp_dir("env");
+        env::set_var("XDG_CONFIG_HOME", &dir);
+        assert_eq!(TumblrClient::credentials_path(), Some(dir.join("rumblr").join("credentials.toml")));
+
+        TumblrClient::with_keys("ck", "cs", "ak", "as").save_profile(None).unwrap();
+        TumblrClient::with_keys("ck2", "cs2", "", "").save_profile(Some("side")).unwrap();
+
+        env::set_var("RUMBLR_ACCESS_KEY", "env-ak");
+        assert_eq!(keys(&TumblrClient::new().load_profile(None).unwrap()), ["ck", "cs", "env-ak", "as"]);
+        env::set_var("RUMBLR_PROFILE", "side");
+        assert_eq!(keys(&TumblrClient::new().load_profile(None).unwrap()), ["ck2", "cs2", "env-ak", ""]);
+
+        // the environment alone is enough
+        env::set_var("RUMBLR_CONSUMER_KEY", "env-ck");
+        env::set_var("RUMBLR_CONSUMER_SECRET", "env-cs");
+        assert_eq!(keys(&TumblrClient::new().load_profile(Some("nobody")).unwrap()), ["env-ck", "env-cs", "env-ak", ""]);
+        assert_eq!(keys(&TumblrClient::with_keys("ck", "cs", "ak", "as").load_env()), ["env-ck", "env-cs", "env-ak", "as"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_mode() {
+        // --- std ---
+        use std::os::unix::fs::PermissionsExt;
+
+        let _env = clean_env();
+        let dir = temp_dir("private");
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        let path = dir.join("credentials.toml");
+        TumblrClient::with_keys("ck", "cs", "ak", "as").save_profile_to(&path, None).unwrap();
+        assert_eq!(mode(&path), 0o600);
+
+        // an existing file is made private too
+        let path = dir.join("existing.toml");
+        fs::write(&path, "").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        TumblrClient::with_keys("ck", "cs", "ak", "as").save_profile_to(&path, None).unwrap();
+        assert_eq!(mode(&path), 0o600);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
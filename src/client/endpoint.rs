@@ -0,0 +1,70 @@
+// --- external ---
+use reqwest::{Method, Response};
+use serde::de::DeserializeOwned;
+// --- custom ---
+use super::{
+    TumblrClient, TumblrClientError, parse_json, response,
+    raw::{Auth, FormField, RequestBody},
+    request::Request,
+};
+
+/// A Tumblr API call, declared by what it sends rather than by how.
+///
+/// Every call of `TumblrClient` is an endpoint turned into a request by `call`, so retries, rate limiting, cassettes
+/// and the `on_unauthorized` hook apply to all of them alike. Implement it for calls rumblr doesn't wrap.
+pub trait Endpoint {
+    /// The `response` of Tumblr's envelope.
+    type Response: DeserializeOwned;
+
+    fn method(&self) -> Method { Method::GET }
+
+    /// Relative to the API base url, e.g. `blog/staff/posts`.
+    fn path(&self) -> String;
+
+    fn auth(&self) -> Auth { Auth::OAuth }
+
+    /// The query of a `GET` or of a JSON body; otherwise the form body, or the first fields of a multipart one.
+    fn params(&self) -> Vec<(&str, String)> { vec![] }
+
+    /// Reject invalid params before anything is sent.
+    fn validate(&self) -> Result<(), TumblrClientError> { Ok(()) }
+
+    /// A JSON or multipart body, built last since it may consume uploads.
+    fn body(self) -> Result<RequestBody, TumblrClientError> where Self: Sized { Ok(RequestBody::Empty) }
+}
+
+impl TumblrClient {
+    /// Send `endpoint`, with error statuses turned into errors and its response deserialized.
+    pub fn call<E: Endpoint>(&self, endpoint: E) -> Result<E::Response, TumblrClientError> { response(parse_json(self.call_raw(endpoint)?)?) }
+
+    /// Send `endpoint`, returning the response whatever its status.
+    pub fn call_raw<E: Endpoint>(&self, endpoint: E) -> Result<Response, TumblrClientError> { self.send(&self.endpoint_request(endpoint)?) }
+
+    pub(super) fn endpoint_request<E: Endpoint>(&self, endpoint: E) -> Result<Request, TumblrClientError> {
+        endpoint.validate()?;
+
+        let method = endpoint.method();
+        let path = endpoint.path();
+        let auth = endpoint.auth();
+        let params = endpoint.params()
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v))
+            .collect::<Vec<_>>();
+        let (query, body) = match endpoint.body()? {
+            body if method == Method::GET => (params, body),
+            RequestBody::Empty if params.is_empty() => (params, RequestBody::Empty),
+            RequestBody::Empty => (vec![], RequestBody::Form(params)),
+            RequestBody::Form(form) => (vec![], RequestBody::Form(params.into_iter().chain(form).collect())),
+            RequestBody::Multipart(fields) => (vec![], RequestBody::Multipart(params.into_iter()
+                .map(|(k, v)| (k, FormField::Text(v)))
+                .chain(fields)
+                .collect())),
+            body @ RequestBody::Json(_) => (params, body),
+        };
+        let query = query.iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect::<Vec<_>>();
+
+        self.raw_request(auth, method, &path, &query, body)
+    }
+}
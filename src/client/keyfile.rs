@@ -0,0 +1,214 @@
+// --- std ---
+use std::{
+    fs::{self, File},
+    path::Path,
+};
+// --- external ---
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    XChaCha20Poly1305, XNonce,
+    aead::{Aead, KeyInit, Payload},
+};
+use rand::Rng;
+// --- custom ---
+use super::{Keys, TumblrClient, TumblrClientError, credentials::create_private};
+
+const VERSION: u32 = 1;
+const KDF: &str = "argon2id";
+/// How far above its default an Argon2 cost may go, as the costs are read before the file is authenticated.
+const MAX_COST_FACTOR: u32 = 4;
+
+/// An encrypted key file: the keys as JSON, sealed with XChaCha20-Poly1305 under a key derived from a passphrase by Argon2id.
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    kdf: String,
+    /// Argon2 memory cost in KiB.
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl KeyFile {
+    fn seal(keys: &Keys, passphrase: &str) -> Result<KeyFile, TumblrClientError> {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 24];
+        rand::thread_rng().fill(&mut salt);
+        rand::thread_rng().fill(&mut nonce);
+
+        let mut key_file = KeyFile {
+            version: VERSION,
+            kdf: KDF.to_owned(),
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+            salt: base64::encode(salt),
+            nonce: base64::encode(nonce),
+            ciphertext: String::new(),
+        };
+        let ciphertext = key_file.cipher(passphrase)?
+            .encrypt(XNonce::from_slice(&nonce), Payload { msg: &serde_json::to_vec(keys)?, aad: key_file.aad().as_bytes() })
+            .map_err(|_| TumblrClientError::KeyFile { msg: "encryption failed".to_owned() })?;
+        key_file.ciphertext = base64::encode(ciphertext);
+
+        Ok(key_file)
+    }
+
+    fn open(&self, passphrase: &str) -> Result<Keys, TumblrClientError> {
+        if self.version != VERSION || self.kdf != KDF {
+            return Err(TumblrClientError::KeyFile { msg: format!("unsupported key file version {} ({})", self.version, self.kdf) });
+        }
+
+        let nonce = decode(&self.nonce)?;
+        if nonce.len() != 24 { return Err(TumblrClientError::KeyFile { msg: "invalid nonce".to_owned() }); }
+        let keys = self.cipher(passphrase)?
+            .decrypt(XNonce::from_slice(&nonce), Payload { msg: &decode(&self.ciphertext)?, aad: self.aad().as_bytes() })
+            .map_err(|_| TumblrClientError::WrongPassphrase)?;
+
+        Ok(serde_json::from_slice(&keys)?)
+    }
+
+    fn cipher(&self, passphrase: &str) -> Result<XChaCha20Poly1305, TumblrClientError> {
+        if self.m_cost > Params::DEFAULT_M_COST * MAX_COST_FACTOR
+            || self.t_cost > Params::DEFAULT_T_COST * MAX_COST_FACTOR
+            || self.p_cost > Params::DEFAULT_P_COST * MAX_COST_FACTOR {
+            return Err(TumblrClientError::KeyFile { msg: format!("Argon2 costs m={} t={} p={} are too high", self.m_cost, self.t_cost, self.p_cost) });
+        }
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32)).map_err(invalid_key_file)?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &decode(&self.salt)?, &mut key)
+            .map_err(invalid_key_file)?;
+
+        Ok(XChaCha20Poly1305::new(&key.into()))
+    }
+
+    /// Everything but the ciphertext is authenticated too, so the KDF parameters cannot be tampered with.
+    fn aad(&self) -> String { format!("rumblr-keys:{}:{}:{}:{}:{}:{}", self.version, self.kdf, self.m_cost, self.t_cost, self.p_cost, self.salt) }
+}
+
+impl TumblrClient {
+    /// Like `save_keys`, encrypted with `passphrase`.
+    pub fn save_keys_encrypted(&self, path: &str, passphrase: &str) -> Result<(), TumblrClientError> { write(Path::new(path), &self.keys, passphrase) }
+
+    /// Counterpart of `save_keys_encrypted`, failing with `WrongPassphrase` if `passphrase` does not decrypt the file.
+    pub fn load_keys_encrypted(mut self, path: &str, passphrase: &str) -> Result<Self, TumblrClientError> {
+        let key_file: KeyFile = serde_json::from_reader(File::open(path)?)
+            .map_err(|_| TumblrClientError::KeyFile { msg: format!("{} is not an encrypted key file, see `migrate_keys`", path) })?;
+        self.keys = key_file.open(passphrase)?;
+
+        Ok(self)
+    }
+
+    /// Encrypt a plaintext key file written by `save_keys` into `encrypted_path`, which may be `path` itself.
+    pub fn migrate_keys(path: &str, encrypted_path: &str, passphrase: &str) -> Result<(), TumblrClientError> {
+        let keys: Keys = serde_json::from_reader(File::open(path)?)?;
+        // Written aside first, so a failure never loses the keys when migrating in place.
+        let tmp = format!("{}.tmp", encrypted_path);
+        write(Path::new(&tmp), &keys, passphrase)?;
+        fs::rename(&tmp, encrypted_path)?;
+
+        Ok(())
+    }
+}
+
+fn write(path: &Path, keys: &Keys, passphrase: &str) -> Result<(), TumblrClientError> {
+    serde_json::to_writer(create_private(path)?, &KeyFile::seal(keys, passphrase)?)?;
+
+    Ok(())
+}
+
+fn decode(s: &str) -> Result<Vec<u8>, TumblrClientError> { base64::decode(s).map_err(invalid_key_file) }
+
+fn invalid_key_file<E: ToString>(e: E) -> TumblrClientError { TumblrClientError::KeyFile { msg: e.to_string() } }
+
+#[cfg(test)]
+mod tests {
+    // --- custom ---
+    use super::*;
+    use crate::client::temp_dir;
+
+    fn keys() -> Keys {
+        Keys {
+            consumer_key: "ck".to_owned(),
+            consumer_secret: "cs".to_owned(),
+            access_key: "ak".to_owned(),
+            access_secret: "as".to_owned(),
+        }
+    }
+
+    fn assert_keys(keys: &Keys) {
+        assert_eq!(
+            (&*keys.consumer_key, &*keys.consumer_secret, &*keys.access_key, &*keys.access_secret),
+            ("ck", "cs", "ak", "as"),
+        );
+    }
+
+    #[test]
+    fn seal_and_open() {
+        let key_file = KeyFile::seal(&keys(), "correct horse").unwrap();
+        assert_keys(&key_file.open("correct horse").unwrap());
+        assert!(matches!(key_file.open("battery staple"), Err(TumblrClientError::WrongPassphrase)));
+    }
+
+    #[test]
+    fn tampering_fails() {
+        let key_file = KeyFile::seal(&keys(), "correct horse").unwrap();
+        let tampered = |tamper: fn(&mut KeyFile)| {
+            let mut key_file = serde_json::from_value::<KeyFile>(serde_json::to_value(&key_file).unwrap()).unwrap();
+            tamper(&mut key_file);
+
+            key_file.open("correct horse")
+        };
+
+        assert_keys(&tampered(|_| {}).unwrap());
+        let ciphertext = tampered(|key_file| {
+            let mut ciphertext = base64::decode(&key_file.ciphertext).unwrap();
+            ciphertext[0] ^= 1;
+            key_file.ciphertext = base64::encode(ciphertext);
+        });
+        assert!(matches!(ciphertext, Err(TumblrClientError::WrongPassphrase)));
+
+        // a weaker KDF, authenticated as associated data
+        assert!(matches!(tampered(|key_file| key_file.t_cost = 1), Err(TumblrClientError::WrongPassphrase)));
+        assert!(matches!(tampered(|key_file| key_file.m_cost /= 2), Err(TumblrClientError::WrongPassphrase)));
+        assert!(matches!(tampered(|key_file| key_file.version = 2), Err(TumblrClientError::KeyFile { .. })));
+    }
+
+    #[test]
+    fn oversized_costs() {
+        let key_file = KeyFile::seal(&keys(), "correct horse").unwrap();
+        let oversized = |tamper: fn(&mut KeyFile)| {
+            let mut key_file = serde_json::from_value::<KeyFile>(serde_json::to_value(&key_file).unwrap()).unwrap();
+            tamper(&mut key_file);
+
+            matches!(key_file.open("correct horse"), Err(TumblrClientError::KeyFile { ref msg }) if msg.contains("too high"))
+        };
+
+        // rejected before a gigabyte is allocated or the hash spins for minutes
+        assert!(oversized(|key_file| key_file.m_cost = 4 * 1024 * 1024));
+        assert!(oversized(|key_file| key_file.t_cost = 1_000));
+        assert!(oversized(|key_file| key_file.p_cost = 64));
+    }
+
+    #[test]
+    fn migrate() {
+        let dir = temp_dir("keyfile");
+        let path = dir.join("keys.json");
+        let path = path.to_str().unwrap();
+        let mut client = TumblrClient::new();
+        client.keys = keys();
+        client.save_keys(path).unwrap();
+
+        // in place
+        TumblrClient::migrate_keys(path, path, "correct horse").unwrap();
+        assert!(fs::read_to_string(path).unwrap().contains("\"ciphertext\""), "no longer plaintext");
+        assert_keys(&TumblrClient::new().load_keys_encrypted(path, "correct horse").unwrap().keys);
+        assert!(matches!(TumblrClient::new().load_keys_encrypted(path, "wrong"), Err(TumblrClientError::WrongPassphrase)));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
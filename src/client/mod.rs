@@ -1,8 +1,17 @@
-mod oauth;
+pub mod oauth;
+pub mod oauth2;
+pub mod credentials;
+pub mod keyfile;
 
 macro_rules! set_attr {
     ($self_:ident, $attr:ident) => {
-        pub fn $attr(mut $self_, $attr: &'a str) -> Self {
+        pub fn $attr(mut $self_, $attr: &str) -> Self {
+            $self_.$attr = Some($attr.to_owned());
+            $self_
+        }
+    };
+    ($self_:ident, $attr:ident: $ty:ty) => {
+        pub fn $attr(mut $self_, $attr: $ty) -> Self {
             $self_.$attr = Some($attr);
             $self_
         }
@@ -12,31 +21,59 @@ macro_rules! set_attr {
 macro_rules! set_params {
     [$(($name:expr, $value:expr)),*] => {{
         let mut v = Vec::new();
-        $(if let Some(value) = $value { v.push(($name, value)); })*
+        $(if let Some(value) = $value { v.push(($name, value.to_string())); })*
 
         v
     }}
 }
 
-macro_rules! build_url { ($url:expr, [$(($name:expr, $value:expr)),*]) => { $(if let Some(value) = $value { $url += &format!("&{}={}", $name, value); })* }; }
-
 pub mod user;
 pub mod blog;
+pub mod retry;
+pub mod rate_limit;
+pub mod model;
+pub mod paginate;
+pub mod builder;
+pub mod cassette;
+pub mod raw;
+pub mod endpoint;
+#[cfg(feature = "async")]
+pub mod asynchronous;
+mod params;
+mod request;
 
 // --- std ---
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    fmt,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
 };
 // --- external ---
+use chrono::Utc;
 use oauth_client::Token;
+use rand::{Rng, distributions::Alphanumeric};
 use reqwest::{
-    Client, ClientBuilder, Proxy, Response,
+    Client, Response, StatusCode, Url,
     header::{AUTHORIZATION, HeaderMap},
 };
-use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+// --- custom ---
+use self::{
+    builder::TumblrClientBuilder,
+    cassette::Tape,
+    model::Envelope,
+    oauth2::{OAuth2, OAuth2Token, is_bearer},
+    params::{build_params, encode, signature},
+    rate_limit::{RateLimit, RateLimiter, Throttle, retry_after},
+    request::Request,
+    retry::{Attempt, RetryPolicy, WritePolicy, classify},
+};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 struct Keys {
     consumer_key: String,
     consumer_secret: String,
@@ -44,6 +81,26 @@ struct Keys {
     access_secret: String,
 }
 
+impl Default for Keys {
+    fn default() -> Self { Keys::new() }
+}
+
+/// Where the API and the OAuth authorization pages live, e.g. a caching gateway or a local fake Tumblr.
+#[derive(Debug, Clone)]
+struct BaseUrls {
+    api: String,
+    oauth: String,
+}
+
+impl Default for BaseUrls {
+    fn default() -> Self {
+        BaseUrls {
+            api: "https://api.tumblr.com/v2/".to_owned(),
+            oauth: "https://www.tumblr.com/".to_owned(),
+        }
+    }
+}
+
 impl Keys {
     fn new() -> Keys {
         Keys {
@@ -55,16 +112,110 @@ impl Keys {
     }
 }
 
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ApiError {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub code: Option<u64>,
+    #[serde(default)]
+    pub detail: String,
+}
+
 #[derive(Debug, Fail)]
 pub enum TumblrClientError {
     #[fail(display = "Invalid proxy address: {}", address)]
-    InvalidProxyAddress { address: String }
+    InvalidProxyAddress { address: String },
+    #[fail(display = "Transport error: {}", _0)]
+    Transport(#[cause] reqwest::Error),
+    #[fail(display = "HTTP status {}: {}", status, body)]
+    HttpStatus { status: u16, body: String },
+    #[fail(display = "Invalid JSON response: {}", _0)]
+    Json(#[cause] serde_json::Error),
+    #[fail(display = "Tumblr API error {}: {}", status, msg)]
+    Api { status: u16, msg: String, errors: Vec<ApiError> },
+    #[fail(display = "OAuth error: {}", msg)]
+    OAuth { msg: String },
+    #[fail(display = "Invalid client configuration: {}", msg)]
+    Config { msg: String },
+    #[fail(display = "Invalid input: {}", msg)]
+    InvalidInput { msg: String },
+    #[fail(display = "IO error: {}", _0)]
+    Io(#[cause] std::io::Error),
+    #[fail(display = "Invalid key file: {}", msg)]
+    KeyFile { msg: String },
+    #[fail(display = "Wrong passphrase, or the key file was tampered with")]
+    WrongPassphrase,
+    #[fail(display = "Cassette error: {}", msg)]
+    Cassette { msg: String },
+    /// Waiting out the throttle or an exhausted quota would go past the `RetryPolicy` deadline.
+    #[fail(display = "Rate limited for {:?}, past the retry deadline", wait)]
+    RateLimited { wait: Duration },
+    #[cfg(feature = "async")]
+    #[fail(display = "Timer error: {}", _0)]
+    Timer(#[cause] tokio_timer::Error),
+}
+
+impl TumblrClientError {
+    /// Whether Tumblr answered `401`, i.e. the credentials are invalid, expired or revoked.
+    pub fn is_unauthorized(&self) -> bool {
+        match self {
+            TumblrClientError::HttpStatus { status, .. } | TumblrClientError::Api { status, .. } => *status == 401,
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for TumblrClientError {
+    fn from(e: reqwest::Error) -> Self { TumblrClientError::Transport(e) }
+}
+
+impl From<serde_json::Error> for TumblrClientError {
+    fn from(e: serde_json::Error) -> Self { TumblrClientError::Json(e) }
+}
+
+impl From<std::io::Error> for TumblrClientError {
+    fn from(e: std::io::Error) -> Self { TumblrClientError::Io(e) }
 }
 
-#[derive(Debug)]
+#[cfg(feature = "async")]
+impl From<tokio_timer::Error> for TumblrClientError {
+    fn from(e: tokio_timer::Error) -> Self { TumblrClientError::Timer(e) }
+}
+
+/// A callback shared by a client and its clones.
+pub(super) struct Hook<T: ?Sized>(Arc<dyn Fn(&T) + Send + Sync>);
+
+impl<T: ?Sized> Hook<T> {
+    fn new<F: Fn(&T) + Send + Sync + 'static>(hook: F) -> Hook<T> { Hook(Arc::new(hook)) }
+
+    fn call(&self, arg: &T) { (self.0)(arg) }
+}
+
+impl<T: ?Sized> Clone for Hook<T> {
+    fn clone(&self) -> Self { Hook(self.0.clone()) }
+}
+
+impl<T: ?Sized> fmt::Debug for Hook<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("Hook") }
+}
+
+#[derive(Debug, Clone)]
 pub struct TumblrClient {
     pub session: Client,
     keys: Keys,
+    base_urls: BaseUrls,
+    retry_policy: RetryPolicy,
+    write_policy: WritePolicy,
+    rate_limiter: RateLimiter,
+    oauth2: Option<Arc<OAuth2>>,
+    on_token_refresh: Option<Hook<OAuth2Token>>,
+    on_unauthorized: Option<Hook<str>>,
+    tape: Option<Arc<Tape>>,
+}
+
+impl Default for TumblrClient {
+    fn default() -> Self { TumblrClient::new() }
 }
 
 impl TumblrClient {
@@ -72,48 +223,183 @@ impl TumblrClient {
         TumblrClient {
             session: Client::new(),
             keys: Keys::new(),
+            base_urls: BaseUrls::default(),
+            retry_policy: RetryPolicy::default(),
+            write_policy: WritePolicy::default(),
+            rate_limiter: RateLimiter::default(),
+            oauth2: None,
+            on_token_refresh: None,
+            on_unauthorized: None,
+            tape: None,
         }
     }
 
+    /// Shorthand for a client built by `TumblrClient::builder().proxy(address)`, keeping the keys and policies.
     pub fn proxy(mut self, address: &str) -> Result<Self, TumblrClientError> {
-        if let Ok(address) = Proxy::https(address) {
-            self.session = ClientBuilder::new()
-                .proxy(address)
-                .build()
-                .unwrap();
+        self.session = TumblrClientBuilder::new().proxy(address).session()?;
+
+        Ok(self)
+    }
+
+    /// Send API requests to `url` instead of `https://api.tumblr.com/v2/`. OAuth signatures cover the configured url.
+    pub fn api_base_url(mut self, url: &str) -> Result<Self, TumblrClientError> {
+        self.base_urls.api = base_url(url)?;
+        Ok(self)
+    }
+
+    /// Use the OAuth endpoints and authorization pages under `url` instead of `https://www.tumblr.com/`.
+    pub fn oauth_base_url(mut self, url: &str) -> Result<Self, TumblrClientError> {
+        self.base_urls.oauth = base_url(url)?;
+        Ok(self)
+    }
+
+    fn api_url(&self, path: &str) -> String { format!("{}{}", self.base_urls.api, path) }
+
+    fn oauth_url(&self, path: &str) -> String { format!("{}{}", self.base_urls.oauth, path) }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn write_policy(mut self, write_policy: WritePolicy) -> Self {
+        self.write_policy = write_policy;
+        self
+    }
 
-            Ok(self)
-        } else { Err(TumblrClientError::InvalidProxyAddress { address: address.to_owned() }) }
+    pub fn throttle(mut self, throttle: Throttle) -> Self {
+        self.rate_limiter.throttle = Some(throttle.into());
+        self
     }
 
-    fn get(&self, url: &str, headers: Option<HeaderMap>) -> Response {
-        let headers = if let Some(headers) = headers { headers } else { HeaderMap::new() };
+    /// Called with the url of every request Tumblr answered `401`, e.g. to trigger re-authentication.
+    pub fn on_unauthorized<F: Fn(&str) + Send + Sync + 'static>(mut self, on_unauthorized: F) -> Self {
+        self.on_unauthorized = Some(Hook::new(on_unauthorized));
+        self
+    }
+
+    /// The quota Tumblr reported for the current consumer key on the latest response.
+    pub fn rate_limit(&self) -> Option<RateLimit> { self.rate_limiter.get(&self.keys.consumer_key) }
+
+    /// Headers authorizing a request with the OAuth2 token if there is one, otherwise signed with the OAuth1 keys.
+    fn auth_headers(&self, method: &str, uri: &str, params: Vec<(&str, &str)>) -> Result<HeaderMap, TumblrClientError> {
+        match self.oauth2 {
+            Some(ref oauth2) => oauth2.headers(),
+            None => build_oauth_headers(method, uri, &self.keys.consumer(), Some(&self.keys.token()), params),
+        }
+    }
+
+    fn send(&self, request: &Request) -> Result<Response, TumblrClientError> {
+        let used = self.oauth2.as_ref().map(|oauth2| oauth2.token().access_token);
+        let mut resp = self.retry(request)?;
+        if self.refresh_if_refused(request, &resp, used.as_deref())? { resp = self.retry(request)?; }
+        self.unauthorized(request, &resp);
+
+        Ok(resp)
+    }
+
+    /// Refresh the OAuth2 token `used` if Tumblr refused `request` with `401` for it, returning whether to send it again.
+    fn refresh_if_refused(&self, request: &Request, resp: &Response, used: Option<&str>) -> Result<bool, TumblrClientError> {
+        match self.oauth2 {
+            Some(ref oauth2) if resp.status() == StatusCode::UNAUTHORIZED && oauth2.refreshable() && is_bearer(request) => {
+                self.refresh_stale_oauth2_token(used)?;
+
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn unauthorized(&self, request: &Request, resp: &Response) {
+        if let Some(ref on_unauthorized) = self.on_unauthorized {
+            if resp.status() == StatusCode::UNAUTHORIZED { on_unauthorized.call(&request.url); }
+        }
+    }
+
+    fn retry(&self, request: &Request) -> Result<Response, TumblrClientError> {
+        let idempotent = request.idempotent();
+        let retry_policy = self.retry_policy.for_request(&self.write_policy, idempotent);
+
+        let started = Instant::now();
+        let mut attempt = 0;
         loop {
-            let headers = headers.clone();
-            match self.session.get(url)
-                .headers(headers)
-                .send() {
-                Ok(resp) => return resp,
-                Err(e) => {
-                    println!("{:?}", e);
-                    continue;
-                }
+            attempt += 1;
+            let retry = match classify(self.dispatch(request, retry_policy.deadline_at(started))?) {
+                Attempt::Done(resp) => return resp.map_err(TumblrClientError::from),
+                Attempt::Rejected(resp) => resp,
+                Attempt::Ambiguous(resp) => if idempotent { resp } else { return resp.map_err(TumblrClientError::from); },
+            };
+
+            match retry_policy.next_delay(started, attempt, retry.as_ref().ok().and_then(|resp| retry_after(resp.headers()))) {
+                Some(delay) => thread::sleep(delay),
+                None => return retry.map_err(TumblrClientError::from),
+            }
+        }
+    }
+
+    /// Send `request` once, waiting for the rate limiter first unless that would end after `deadline`.
+    fn dispatch(&self, request: &Request, deadline: Option<Instant>) -> Result<reqwest::Result<Response>, TumblrClientError> {
+        let request = self.authorize(request)?;
+        let resp = match self.tape {
+            Some(ref tape) if tape.replaying() => Ok(tape.replay(&request)?),
+            _ => {
+                let builder = request.build(&self.session)?;
+                if let Some(wait) = self.rate_limiter.wait(&self.keys.consumer_key, deadline)? { thread::sleep(wait); }
+
+                builder.send()
+            }
+        };
+        if let Ok(ref resp) = resp { self.rate_limiter.record(&self.keys.consumer_key, resp.headers()); }
+
+        match (resp, &self.tape) {
+            (Ok(resp), Some(tape)) if !tape.replaying() => tape.record(&request, resp),
+            (resp, _) => Ok(resp),
+        }
+    }
+
+    /// Put the current OAuth2 token on a bearer authorized request, refreshing it first if it expired.
+    fn authorize<'r>(&self, request: &'r Request) -> Result<Cow<'r, Request>, TumblrClientError> {
+        match self.oauth2 {
+            Some(ref oauth2) if is_bearer(request) => {
+                if let Some(stale) = oauth2.expired() { self.refresh_stale_oauth2_token(Some(&stale))?; }
+
+                let mut request = request.clone();
+                request.headers.extend(oauth2.headers()?);
+
+                Ok(Cow::Owned(request))
             }
+            _ => Ok(Cow::Borrowed(request)),
         }
     }
 
-    fn post<T: Serialize>(&self, url: &str, headers: HeaderMap, form: &T) -> Response {
+    fn create<L>(&self, request: &Request, lookup: L) -> Result<Value, TumblrClientError>
+        where L: Fn() -> Result<Option<Value>, TumblrClientError>
+    {
+        let used = self.oauth2.as_ref().map(|oauth2| oauth2.token().access_token);
+        let mut refreshed = false;
+        let started = Instant::now();
+        let mut attempt = 0;
         loop {
-            let headers = headers.clone();
-            match self.session.post(url)
-                .headers(headers)
-                .form(form)
-                .send() {
-                Ok(resp) => return resp,
-                Err(e) => {
-                    println!("{:?}", e);
-                    continue;
+            attempt += 1;
+            let retry = match classify(self.dispatch(request, self.retry_policy.deadline_at(started))?) {
+                Attempt::Done(resp) => {
+                    let resp = resp?;
+                    // A refused post was not created, so it is sent again as is.
+                    if !refreshed && self.refresh_if_refused(request, &resp, used.as_deref())? {
+                        refreshed = true;
+                        continue;
+                    }
+                    self.unauthorized(request, &resp);
+
+                    return parse_json(resp);
                 }
+                Attempt::Rejected(resp) => resp,
+                Attempt::Ambiguous(resp) => if let Some(created) = lookup()? { return Ok(created); } else { resp },
+            };
+
+            match self.retry_policy.next_delay(started, attempt, retry.as_ref().ok().and_then(|resp| retry_after(resp.headers()))) {
+                Some(delay) => thread::sleep(delay),
+                None => return parse_json(retry?),
             }
         }
     }
@@ -133,36 +419,93 @@ impl TumblrClient {
 //    }
 }
 
+/// The `OAuth` authorization header of a request carrying `params`, signed with `HMAC-SHA1`.
 fn build_oauth_headers(
     method: &str,
     uri: &str,
-    consumer: &Token,
-    token: Option<&Token>,
-    other_param: Option<&HashMap<Cow<str>, Cow<str>>>,
-) -> HeaderMap {
-    let (header, _) = oauth_client::authorization_header(method, uri, consumer, token, other_param);
+    consumer: &Token<'_>,
+    token: Option<&Token<'_>>,
+    params: Vec<(&str, &str)>,
+) -> Result<HeaderMap, TumblrClientError> {
+    let nonce = rand::thread_rng().sample_iter(&Alphanumeric).take(32).collect::<String>();
+    let timestamp = Utc::now().timestamp().to_string();
+    let mut oauth = vec![
+        ("oauth_consumer_key", consumer.key.as_ref()),
+        ("oauth_nonce", nonce.as_str()),
+        ("oauth_signature_method", "HMAC-SHA1"),
+        ("oauth_timestamp", timestamp.as_str()),
+        ("oauth_version", "1.0"),
+    ];
+    if let Some(token) = token { oauth.push(("oauth_token", token.key.as_ref())); }
+    // such as `oauth_callback` and `oauth_verifier`, which go in the header too
+    let (extra, params): (Vec<_>, Vec<_>) = params.into_iter().partition(|(k, _)| k.starts_with("oauth_"));
+    oauth.extend(extra);
+
+    let signed = build_params(oauth.iter().cloned().chain(params).collect());
+    let signature = signature(method, uri, &signed, &consumer.secret, token.map_or("", |token| token.secret.as_ref()));
+    oauth.push(("oauth_signature", signature.as_str()));
+    oauth.sort_unstable();
+    let header = format!("OAuth {}", oauth.iter().map(|(k, v)| format!("{}=\"{}\"", k, encode(v))).collect::<Vec<_>>().join(", "));
 
     let mut headers = HeaderMap::new();
-    headers.insert(AUTHORIZATION, header.parse().unwrap());
+    headers.insert(
+        AUTHORIZATION,
+        header.parse().map_err(|_| TumblrClientError::OAuth { msg: "invalid authorization header".to_owned() })?,
+    );
+
+    Ok(headers)
+}
+
+/// Validate a base url, which gets a trailing `/` so paths can be appended to it.
+fn base_url(url: &str) -> Result<String, TumblrClientError> {
+    match Url::parse(url) {
+        Ok(ref parsed) if (parsed.scheme() == "http" || parsed.scheme() == "https") && parsed.has_host() => {
+            Ok(if url.ends_with('/') { url.to_owned() } else { format!("{}/", url) })
+        }
+        _ => Err(TumblrClientError::Config { msg: format!("invalid base url {}", url) }),
+    }
+}
+
+fn parse_json(mut resp: Response) -> Result<Value, TumblrClientError> {
+    let status = resp.status();
+    let body = resp.text()?;
 
-    headers
+    parse_body(status, body)
 }
 
-fn build_query(api: &str, params: &[(&str, &str)]) -> String {
-    if params.is_empty() { api.to_owned() } else {
-        format!(
-            "{}?{}",
-            api,
-            params.iter()
-                .map(|(k, v)| format!("{}={}", k, v))
-                .collect::<Vec<String>>()
-                .join("&")
-        )
+fn response<T: DeserializeOwned>(value: Value) -> Result<T, TumblrClientError> {
+    Ok(serde_json::from_value::<Envelope<T>>(value)?.response)
+}
+
+fn parse_body(status: StatusCode, body: String) -> Result<Value, TumblrClientError> {
+    match serde_json::from_str::<Value>(&body) {
+        Ok(value) => {
+            let meta_status = value["meta"]["status"].as_u64().map(|status| status as u16);
+            if status.is_success() && meta_status.is_none_or(|status| status < 400) { return Ok(value); }
+
+            let errors = value["errors"].as_array()
+                .map(|errors| errors.iter()
+                    .filter_map(|error| serde_json::from_value(error.clone()).ok())
+                    .collect())
+                .unwrap_or_default();
+
+            Err(TumblrClientError::Api {
+                status: meta_status.unwrap_or_else(|| status.as_u16()),
+                msg: value["meta"]["msg"].as_str().unwrap_or_else(|| status.canonical_reason().unwrap_or("")).to_owned(),
+                errors,
+            })
+        }
+        Err(_) if !status.is_success() => Err(TumblrClientError::HttpStatus { status: status.as_u16(), body }),
+        Err(e) => Err(e.into()),
     }
 }
 
-fn build_params<'a>(params: Vec<(&'a str, &'a str)>) -> HashMap<Cow<'a, str>, Cow<'a, str>> {
-    params.into_iter()
-        .map(|(k, v)| (k.into(), v.into()))
-        .collect()
+/// A fresh, empty directory for the files of a test.
+#[cfg(test)]
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("rumblr-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    dir
 }
@@ -0,0 +1,282 @@
+// --- external ---
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+// --- custom ---
+use super::blog::{ContentBlock, Layout};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub meta: Meta,
+    pub response: T,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Meta {
+    pub status: u16,
+    pub msg: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct User {
+    pub name: String,
+    pub likes: u64,
+    pub following: u64,
+    pub default_post_format: String,
+    pub blogs: Vec<Blog>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Blog {
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    pub url: String,
+    pub uuid: String,
+    pub updated: u64,
+    pub posts: Option<u64>,
+    pub likes: Option<u64>,
+    pub followers: Option<u64>,
+    pub primary: Option<bool>,
+    pub admin: Option<bool>,
+    pub ask: Option<bool>,
+    pub ask_anon: Option<bool>,
+    pub is_nsfw: Option<bool>,
+    pub avatar: Vec<PhotoSize>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Post {
+    pub blog_name: String,
+    #[serde(deserialize_with = "id")]
+    pub id: u64,
+    pub id_string: String,
+    pub post_url: String,
+    pub slug: String,
+    pub date: String,
+    pub timestamp: u64,
+    pub state: String,
+    pub format: String,
+    pub reblog_key: String,
+    pub tags: Vec<String>,
+    pub short_url: String,
+    pub summary: String,
+    pub note_count: u64,
+    pub source_url: Option<String>,
+    pub source_title: Option<String>,
+    pub liked: Option<bool>,
+    pub liked_timestamp: Option<u64>,
+    pub notes: Vec<Note>,
+    /// NPF content, for posts fetched with `npf=true`.
+    #[serde(rename = "content", skip_serializing_if = "Vec::is_empty")]
+    pub blocks: Vec<ContentBlock>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub layout: Vec<Layout>,
+    #[serde(flatten)]
+    pub content: PostContent,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The type specific part of a legacy post, tagged by its `type`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PostContent {
+    Text {
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        body: String,
+    },
+    Photo {
+        #[serde(default)]
+        caption: String,
+        #[serde(default)]
+        photos: Vec<Photo>,
+    },
+    Quote {
+        #[serde(default)]
+        text: String,
+        #[serde(default)]
+        source: String,
+    },
+    Link {
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        url: String,
+        #[serde(default)]
+        author: Option<String>,
+        #[serde(default)]
+        excerpt: Option<String>,
+        #[serde(default)]
+        publisher: Option<String>,
+        #[serde(default)]
+        description: String,
+        #[serde(default)]
+        photos: Vec<Photo>,
+    },
+    Chat {
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        body: String,
+        #[serde(default)]
+        dialogue: Vec<Dialogue>,
+    },
+    Audio {
+        #[serde(default)]
+        caption: String,
+        #[serde(default)]
+        player: String,
+        #[serde(default)]
+        plays: u64,
+        #[serde(default)]
+        album_art: Option<String>,
+        #[serde(default)]
+        artist: Option<String>,
+        #[serde(default)]
+        album: Option<String>,
+        #[serde(default)]
+        track_name: Option<String>,
+        #[serde(default)]
+        track_number: Option<u64>,
+        #[serde(default)]
+        year: Option<u64>,
+    },
+    Video {
+        #[serde(default)]
+        caption: String,
+        #[serde(default)]
+        player: Vec<VideoPlayer>,
+    },
+    Answer {
+        #[serde(default)]
+        asking_name: String,
+        #[serde(default)]
+        asking_url: Option<String>,
+        #[serde(default)]
+        question: String,
+        #[serde(default)]
+        answer: String,
+    },
+    /// Any type this crate does not know yet, see `Post::extra` for its fields.
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Photo {
+    pub caption: String,
+    pub original_size: PhotoSize,
+    pub alt_sizes: Vec<PhotoSize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhotoSize {
+    pub width: u32,
+    pub height: u32,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Dialogue {
+    pub name: String,
+    pub label: String,
+    pub phrase: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoPlayer {
+    pub width: Value,
+    pub embed_code: Value,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Note {
+    pub r#type: String,
+    pub timestamp: u64,
+    pub blog_name: String,
+    pub blog_uuid: String,
+    pub blog_url: String,
+    pub post_id: Option<String>,
+    pub added_text: Option<String>,
+    pub reply_text: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Follower {
+    pub name: String,
+    pub url: String,
+    pub updated: u64,
+    pub following: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserInfo { pub user: User }
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlogInfo { pub blog: Blog }
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Posts {
+    pub blog: Option<Blog>,
+    pub posts: Vec<Post>,
+    pub total_posts: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Likes {
+    pub liked_posts: Vec<Post>,
+    pub liked_count: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Following {
+    pub total_blogs: u64,
+    pub blogs: Vec<Blog>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Followers {
+    pub total_users: u64,
+    pub users: Vec<Follower>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PostId {
+    #[serde(deserialize_with = "id")]
+    pub id: u64,
+}
+
+/// Tumblr sends post ids either as numbers or, for ids beyond 2^53, as strings.
+fn id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    // --- external ---
+    use serde::de::Error;
+
+    match Value::deserialize(deserializer)? {
+        Value::Number(id) => id.as_u64().ok_or_else(|| D::Error::custom("invalid post id")),
+        Value::String(id) => id.parse().map_err(D::Error::custom),
+        Value::Null => Ok(0),
+        _ => Err(D::Error::custom("invalid post id")),
+    }
+}
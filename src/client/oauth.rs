@@ -1,7 +1,7 @@
 mod api {
-    pub const REQUEST_TOKEN: &'static str = "https://www.tumblr.com/oauth/request_token";
-    pub const AUTHORIZE: &'static str = "https://www.tumblr.com/oauth/authorize";
-    pub const ACCESS_TOKEN: &'static str = "https://www.tumblr.com/oauth/access_token";
+    pub const REQUEST_TOKEN: &str = "oauth/request_token";
+    pub const AUTHORIZE: &str = "oauth/authorize";
+    pub const ACCESS_TOKEN: &str = "oauth/access_token";
 }
 
 
@@ -10,16 +10,48 @@ use std::{
     borrow::Cow,
     collections::HashMap,
     fs::File,
+    io::{BufRead, BufReader, ErrorKind, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
 };
 // --- external ---
 use oauth_client::Token;
 // --- custom ---
-use super::{Keys, TumblrClient, build_oauth_headers};
+use super::{
+    Keys, TumblrClient, TumblrClientError, build_oauth_headers,
+    credentials::create_private,
+    params::build_query,
+    request::Request,
+};
+
+/// How long an accepted loopback connection may take to send its request line.
+const CALLBACK_READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A request token waiting to be authorized, serializable so the flow can continue in another process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestToken {
+    pub key: String,
+    pub secret: String,
+    /// The client's authorization page.
+    #[serde(default = "authorize")]
+    authorize: String,
+}
+
+fn authorize() -> String { format!("https://www.tumblr.com/{}", api::AUTHORIZE) }
+
+impl RequestToken {
+    pub fn authorize_url(&self) -> String { build_query(&self.authorize, &[("oauth_token", &self.key)]) }
+
+    fn token(&self) -> Token<'_> { Token::new(&self.key, &self.secret) }
+}
 
 impl Keys {
-    pub fn consumer(&self) -> Token { Token::new(&self.consumer_key, &self.consumer_secret) }
+    pub fn consumer(&self) -> Token<'_> { Token::new(&self.consumer_key, &self.consumer_secret) }
 
-    pub fn token(&self) -> Token { Token::new(&self.access_key, &self.access_secret) }
+    pub fn token(&self) -> Token<'_> { Token::new(&self.access_key, &self.access_secret) }
 }
 
 impl TumblrClient {
@@ -30,75 +62,107 @@ impl TumblrClient {
         self
     }
 
-    fn request_token(&self) -> Token {
-        let resp = self.get(
-            api::REQUEST_TOKEN,
-            Some(build_oauth_headers(
-                "GET",
-                api::REQUEST_TOKEN,
-                &self.keys.consumer(),
-                None,
-                None,
-            )),
-        ).text().unwrap();
-
-        let params = split_params(&resp);
-        Token::new(
-            params.get("oauth_token").unwrap().to_string(),
-            params.get("oauth_token_secret").unwrap().to_string(),
-        )
+    /// First step of the OAuth flow: obtain a request token, to be authorized by the user at `RequestToken::authorize_url`.
+    ///
+    /// Tumblr redirects to `oauth_callback` once authorized, or to the app's default callback.
+    pub fn request_token(&self, oauth_callback: Option<&str>) -> Result<RequestToken, TumblrClientError> {
+        let (key, secret) = parse_token(&token_response(self.send(&self.request_token_request(oauth_callback)?)?)?)?;
+
+        Ok(self.new_request_token(key, secret))
     }
 
-    fn authorize(token: &str) { println!("OAuth: {}?oauth_token={}", api::AUTHORIZE, token); }
+    pub(super) fn new_request_token(&self, key: String, secret: String) -> RequestToken {
+        RequestToken {
+            key,
+            secret,
+            authorize: self.oauth_url(api::AUTHORIZE),
+        }
+    }
 
-    fn access_token(&self, token: &Token) -> (String, String) {
-        let oauth_verifier = read_line("Url: ");
-        let params = {
-            let oauth_verifier = oauth_verifier.split("oauth_verifier=")
-                .last()
-                .unwrap();
+    pub(super) fn request_token_request(&self, oauth_callback: Option<&str>) -> Result<Request, TumblrClientError> {
+        let params = oauth_callback.map(|oauth_callback| vec![("oauth_callback", oauth_callback)]).unwrap_or_default();
+        let api = self.oauth_url(api::REQUEST_TOKEN);
+        let headers = build_oauth_headers(
+            "GET",
+            &api,
+            &self.keys.consumer(),
+            None,
+            params,
+        )?;
 
-            let mut params = HashMap::new();
-            params.insert("oauth_verifier".into(), oauth_verifier.into());
+        Ok(Request::get(api, Some(headers)))
+    }
 
-            params
-        };
+    /// Last step of the OAuth flow: exchange the authorized `request_token` for access credentials.
+    ///
+    /// `oauth_verifier` is either the verifier itself or the whole url Tumblr redirected to.
+    pub fn access_token(mut self, request_token: &RequestToken, oauth_verifier: &str) -> Result<Self, TumblrClientError> {
+        let (access_key, access_secret) = parse_token(&token_response(self.send(&self.access_token_request(request_token, oauth_verifier)?)?)?)?;
+        self.keys.access_key = access_key;
+        self.keys.access_secret = access_secret;
+
+        Ok(self)
+    }
+
+    pub(super) fn access_token_request(&self, request_token: &RequestToken, oauth_verifier: &str) -> Result<Request, TumblrClientError> {
+        let oauth_verifier = parse_verifier(request_token, oauth_verifier, false)?;
+        let api = self.oauth_url(api::ACCESS_TOKEN);
+        let headers = build_oauth_headers(
+            "GET",
+            &api,
+            &self.keys.consumer(),
+            Some(&request_token.token()),
+            vec![("oauth_verifier", oauth_verifier.as_str())],
+        )?;
+
+        Ok(Request::get(api, Some(headers)))
+    }
+
+    /// Interactive OAuth flow on the terminal: prints the authorization url and reads the redirect url from stdin.
+    pub fn oauth(self) -> Result<Self, TumblrClientError> {
+        let request_token = self.request_token(None)?;
+        println!("OAuth: {}", request_token.authorize_url());
+        let oauth_verifier = read_line("Url: ")?;
 
-        let resp = self.get(
-            api::ACCESS_TOKEN,
-            Some(build_oauth_headers(
-                "GET",
-                api::ACCESS_TOKEN,
-                &self.keys.consumer(),
-                Some(token),
-                Some(&params),
-            )),
-        ).text().unwrap();
-
-        let param = split_params(&resp);
-        (
-            param.get("oauth_token")
-                .unwrap()
-                .to_string(),
-            param.get("oauth_token_secret")
-                .unwrap()
-                .to_string()
-        )
+        self.access_token(&request_token, &oauth_verifier)
     }
 
-    pub fn oauth(mut self) -> Self {
-        let token = self.request_token();
-        TumblrClient::authorize(&token.key);
-        let (access_key, access_secret) = self.access_token(&token);
+    /// OAuth flow completed by a temporary listener on 127.0.0.1, which Tumblr redirects to once the user authorized the app.
+    ///
+    /// `authorize` is given the authorization url to show or open in a browser.
+    /// Fails unless the whole flow, token exchanges included, completes within `timeout`.
+    pub fn oauth_loopback<F: FnOnce(&str)>(mut self, authorize: F, timeout: Duration) -> Result<Self, TumblrClientError> {
+        let deadline = Instant::now() + timeout;
+        let listener = TcpListener::bind(("127.0.0.1", 0)).map_err(io_error)?;
+        let oauth_callback = format!("http://127.0.0.1:{}/callback", listener.local_addr().map_err(io_error)?.port());
 
+        let (key, secret) = parse_token(&self.exchange(self.request_token_request(Some(&oauth_callback))?, deadline)?)?;
+        let request_token = self.new_request_token(key, secret);
+        authorize(&request_token.authorize_url());
+        let oauth_verifier = wait_callback(&listener, &request_token, deadline)?;
+
+        let (access_key, access_secret) = parse_token(&self.exchange(self.access_token_request(&request_token, &oauth_verifier)?, deadline)?)?;
         self.keys.access_key = access_key;
         self.keys.access_secret = access_secret;
 
-        self
+        Ok(self)
+    }
+
+    /// Send a token exchange of `oauth_loopback`, failing once `deadline` passes.
+    ///
+    /// The blocking session has no per-request timeout, so it is sent from a thread which is abandoned at the deadline.
+    fn exchange(&self, request: Request, deadline: Instant) -> Result<String, TumblrClientError> {
+        let timed_out = || TumblrClientError::OAuth { msg: "timed out exchanging the OAuth tokens".to_owned() };
+        let remaining = deadline.checked_duration_since(Instant::now()).ok_or_else(timed_out)?;
+        let client = self.clone().retry_policy(self.retry_policy.clone().deadline(remaining));
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || { let _ = sender.send(client.send(&request).and_then(token_response)); });
+
+        receiver.recv_timeout(remaining).unwrap_or_else(|_| Err(timed_out()))
     }
 
     pub fn save_keys(&self, path: &str) -> Result<(), std::io::Error> {
-        let file = File::create(path)?;
+        let file = create_private(Path::new(path))?;
         serde_json::to_writer(file, &self.keys)?;
 
         Ok(())
@@ -112,28 +176,140 @@ impl TumblrClient {
     }
 }
 
-fn read_line(tips: &str) -> String {
+pub(super) fn read_line(tips: &str) -> Result<String, TumblrClientError> {
     // --- std ---
     use std::io::{Write, stdin, stdout};
 
-    let mut s = String::new();
-    print!("{}", tips);
-    stdout().flush().unwrap();
-    stdin().read_line(&mut s).unwrap();
+    let read = || -> std::io::Result<String> {
+        let mut s = String::new();
+        print!("{}", tips);
+        stdout().flush()?;
+        stdin().read_line(&mut s)?;
 
-    s.trim().to_owned()
+        Ok(s)
+    };
+
+    read()
+        .map(|s| s.trim().to_owned())
+        .map_err(|e| TumblrClientError::OAuth { msg: e.to_string() })
 }
 
-fn split_params(query: &str) -> HashMap<Cow<str>, Cow<str>> {
+fn token_response(mut resp: reqwest::Response) -> Result<String, TumblrClientError> {
+    let status = resp.status();
+    let body = resp.text()?;
+
+    token_body(status, body)
+}
+
+pub(super) fn token_body(status: reqwest::StatusCode, body: String) -> Result<String, TumblrClientError> {
+    if status.is_success() { Ok(body) } else { Err(TumblrClientError::HttpStatus { status: status.as_u16(), body }) }
+}
+
+pub(super) fn parse_token(body: &str) -> Result<(String, String), TumblrClientError> {
+    let params = split_params(body);
+
+    Ok((get_param(&params, "oauth_token")?, get_param(&params, "oauth_token_secret")?))
+}
+
+/// Serve redirects until one carries the verifier for `request_token`.
+fn wait_callback(listener: &TcpListener, request_token: &RequestToken, deadline: Instant) -> Result<String, TumblrClientError> {
+    listener.set_nonblocking(true).map_err(io_error)?;
+
+    loop {
+        let mut stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                if Instant::now() >= deadline { return Err(TumblrClientError::OAuth { msg: "timed out waiting for the OAuth callback".to_owned() }); }
+
+                thread::sleep(Duration::from_millis(50));
+                continue;
+            }
+            Err(e) => return Err(io_error(e)),
+        };
+        stream.set_nonblocking(false).map_err(io_error)?;
+        // so an idle connection can't hold up the redirect until the deadline
+        stream.set_read_timeout(Some(deadline.saturating_duration_since(Instant::now()).clamp(Duration::from_millis(1), CALLBACK_READ_TIMEOUT))).map_err(io_error)?;
+
+        let mut request_line = String::new();
+        if BufReader::new(&stream).read_line(&mut request_line).is_err() { continue; }
+        let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+
+        if !path.starts_with("/callback") {
+            respond(&mut stream, "404 Not Found", "Not found.");
+            continue;
+        }
+        if !path.contains("oauth_verifier=") {
+            let query = path.split_once('?').map_or("", |(_, query)| query);
+            if split_params(query).get("oauth_token").is_some_and(|oauth_token| oauth_token == &request_token.key) {
+                respond(&mut stream, "400 Bad Request", "Authorization was denied, you can close this window.");
+                return Err(TumblrClientError::OAuth { msg: "authorization was denied".to_owned() });
+            }
+
+            // a stray redirect, or a denial of some other request token
+            respond(&mut stream, "400 Bad Request", "Unexpected OAuth token.");
+            continue;
+        }
+
+        match parse_verifier(request_token, path, true) {
+            Ok(oauth_verifier) => {
+                respond(&mut stream, "200 OK", "Authorized, you can close this window.");
+                return Ok(oauth_verifier);
+            }
+            // a redirect for some other request token, keep waiting for ours
+            Err(_) => respond(&mut stream, "400 Bad Request", "Unexpected OAuth token."),
+        }
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, message: &str) {
+    let body = format!("<!DOCTYPE html><html><head><title>rumblr</title></head><body><p>{}</p></body></html>", message);
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body,
+    );
+    let _ = stream.flush();
+}
+
+fn io_error(e: std::io::Error) -> TumblrClientError { TumblrClientError::OAuth { msg: e.to_string() } }
+
+/// Take the verifier from either a bare verifier or the callback url, checking the url belongs to `request_token`.
+///
+/// With `token_required`, as for redirects to the loopback listener, the url must carry the `oauth_token`.
+fn parse_verifier(request_token: &RequestToken, input: &str, token_required: bool) -> Result<String, TumblrClientError> {
+    let input = input.trim();
+    let input = input.split('#').next().unwrap_or_default();
+    if !input.contains("oauth_verifier=") {
+        if input.is_empty() { return Err(TumblrClientError::OAuth { msg: "missing oauth_verifier".to_owned() }); }
+
+        return Ok(input.to_owned());
+    }
+
+    let query = input.rsplit_once('?').map_or(input, |(_, query)| query);
+    let params = split_params(query);
+    match params.get("oauth_token") {
+        Some(oauth_token) if oauth_token != &request_token.key => return Err(TumblrClientError::OAuth { msg: "oauth_token does not match the request token".to_owned() }),
+        None if token_required => return Err(TumblrClientError::OAuth { msg: "missing oauth_token".to_owned() }),
+        _ => {}
+    }
+
+    get_param(&params, "oauth_verifier")
+}
+
+fn split_params(query: &str) -> HashMap<Cow<'_, str>, Cow<'_, str>> {
     let mut params = HashMap::new();
 
     for param in query.split('&') {
-        let mut s = param.splitn(2, '=');
-        let k = s.next().unwrap();
-        let v = s.next().unwrap();
-
-        params.insert(k.into(), v.into());
+        if let Some((k, v)) = param.split_once('=') { params.insert(k.into(), v.into()); }
     }
 
     params
 }
+
+fn get_param(params: &HashMap<Cow<'_, str>, Cow<'_, str>>, name: &str) -> Result<String, TumblrClientError> {
+    params.get(name)
+        .map(|value| value.to_string())
+        .ok_or_else(|| TumblrClientError::OAuth { msg: format!("missing {}", name) })
+}
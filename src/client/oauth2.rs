@@ -0,0 +1,285 @@
+mod api {
+    // under the OAuth base url
+    pub const AUTHORIZE: &str = "oauth2/authorize";
+    // under the API base url
+    pub const TOKEN: &str = "oauth2/token";
+}
+
+// --- std ---
+use std::{
+    fmt,
+    fs::File,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+// --- external ---
+use reqwest::{
+    StatusCode, Url,
+    header::{AUTHORIZATION, HeaderMap, HeaderValue},
+};
+use serde_json::Value;
+// --- custom ---
+use super::{Hook, Keys, TumblrClient, TumblrClientError, credentials::create_private, request::Request};
+
+/// Refresh tokens this long before they expire, so they don't expire in flight.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Basic,
+    Write,
+    /// Grants a refresh token.
+    OfflineAccess,
+}
+
+impl Scope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Basic => "basic",
+            Scope::Write => "write",
+            Scope::OfflineAccess => "offline_access",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuth2Token {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Seconds since the unix epoch.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+impl OAuth2Token {
+    pub fn expired(&self) -> bool {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default() + EXPIRY_MARGIN;
+
+        self.expires_at.is_some_and(|expires_at| Duration::from_secs(expires_at) <= now)
+    }
+}
+
+/// The OAuth2 token of a client, shared with its clones so a refresh benefits all of them.
+pub(super) struct OAuth2 {
+    token: Mutex<OAuth2Token>,
+    /// Held across a refresh, so concurrent requests refresh an expired token once.
+    refreshing: Mutex<()>,
+    on_refresh: Option<Hook<OAuth2Token>>,
+}
+
+impl fmt::Debug for OAuth2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("OAuth2 { .. }") }
+}
+
+impl OAuth2 {
+    pub fn new(token: OAuth2Token, on_refresh: Option<Hook<OAuth2Token>>) -> OAuth2 {
+        OAuth2 {
+            token: Mutex::new(token),
+            refreshing: Mutex::new(()),
+            on_refresh,
+        }
+    }
+
+    pub fn token(&self) -> OAuth2Token { self.token.lock().unwrap_or_else(|e| e.into_inner()).clone() }
+
+    /// The access token, if it has to be refreshed before use.
+    pub fn expired(&self) -> Option<String> {
+        let token = self.token.lock().unwrap_or_else(|e| e.into_inner());
+
+        if token.refresh_token.is_some() && token.expired() { Some(token.access_token.clone()) } else { None }
+    }
+
+    pub fn refreshable(&self) -> bool { self.token.lock().unwrap_or_else(|e| e.into_inner()).refresh_token.is_some() }
+
+    pub fn headers(&self) -> Result<HeaderMap, TumblrClientError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", self.token().access_token))
+                .map_err(|_| TumblrClientError::OAuth { msg: "invalid access token".to_owned() })?,
+        );
+
+        Ok(headers)
+    }
+
+    pub fn refresh_request(&self, token_url: String, keys: &Keys) -> Result<Request, TumblrClientError> {
+        let refresh_token = self.token()
+            .refresh_token
+            .ok_or_else(|| TumblrClientError::OAuth { msg: "no refresh token, request the offline_access scope".to_owned() })?;
+
+        Ok(Request::post(token_url, HeaderMap::new(), &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", &refresh_token),
+            ("client_id", &keys.consumer_key),
+            ("client_secret", &keys.consumer_secret),
+        ]))
+    }
+
+    /// Refresh the token with `refresh`, one refresh at a time.
+    ///
+    /// Given the `stale` access token a request found expired or was refused with, a token another request refreshed
+    /// meanwhile is used instead of refreshing again.
+    pub fn refresh<F>(&self, stale: Option<&str>, refresh: F) -> Result<OAuth2Token, TumblrClientError>
+        where F: FnOnce() -> Result<OAuth2Token, TumblrClientError>
+    {
+        let _refreshing = self.refreshing.lock().unwrap_or_else(|e| e.into_inner());
+        if stale.is_some_and(|stale| self.token().access_token != stale) { return Ok(self.token()); }
+        self.update(refresh()?);
+
+        Ok(self.token())
+    }
+
+    /// Store a refreshed token, keeping the refresh token if Tumblr did not rotate it.
+    pub fn update(&self, mut token: OAuth2Token) {
+        {
+            let mut current = self.token.lock().unwrap_or_else(|e| e.into_inner());
+            if token.refresh_token.is_none() { token.refresh_token = current.refresh_token.take(); }
+            *current = token.clone();
+        }
+
+        if let Some(ref on_refresh) = self.on_refresh { on_refresh.call(&token); }
+    }
+}
+
+/// Whether `request` is authorized with a bearer token, as opposed to OAuth1 or no authorization.
+pub(super) fn is_bearer(request: &Request) -> bool {
+    request.headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("Bearer "))
+}
+
+pub(super) fn parse_token(status: StatusCode, body: &str) -> Result<OAuth2Token, TumblrClientError> {
+    #[derive(Deserialize)]
+    struct TokenResponse {
+        access_token: String,
+        #[serde(default)]
+        refresh_token: Option<String>,
+        #[serde(default)]
+        expires_in: Option<u64>,
+        #[serde(default)]
+        scope: Option<String>,
+    }
+
+    if !status.is_success() {
+        let error = serde_json::from_str::<Value>(body).ok();
+        let msg = error.as_ref()
+            .and_then(|error| error["error_description"].as_str().or_else(|| error["error"].as_str()))
+            .unwrap_or(body);
+
+        return Err(TumblrClientError::OAuth { msg: format!("{}: {}", status.as_u16(), msg) });
+    }
+
+    let token: TokenResponse = serde_json::from_str(body)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+    Ok(OAuth2Token {
+        access_token: token.access_token,
+        refresh_token: token.refresh_token,
+        expires_at: token.expires_in.map(|expires_in| now + expires_in),
+        scope: token.scope,
+    })
+}
+
+impl TumblrClient {
+    /// The url to send the user to, which redirects to `redirect_uri` (or the app's default) with a `code` and `state`.
+    pub fn oauth2_authorize_url(&self, scopes: &[Scope], state: &str, redirect_uri: Option<&str>) -> Result<String, TumblrClientError> {
+        let scope = scopes.iter()
+            .map(|scope| scope.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut params = vec![
+            ("client_id", self.keys.consumer_key.as_str()),
+            ("response_type", "code"),
+            ("scope", &scope),
+            ("state", state),
+        ];
+        if let Some(redirect_uri) = redirect_uri { params.push(("redirect_uri", redirect_uri)); }
+
+        Url::parse_with_params(&self.oauth_url(api::AUTHORIZE), &params)
+            .map(|url| url.into_string())
+            .map_err(|e| TumblrClientError::InvalidInput { msg: e.to_string() })
+    }
+
+    /// Exchange the `code` Tumblr redirected with for tokens, and authorize every following request with them.
+    pub fn oauth2_exchange_code(self, code: &str, redirect_uri: Option<&str>) -> Result<Self, TumblrClientError> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("client_id", self.keys.consumer_key.as_str()),
+            ("client_secret", self.keys.consumer_secret.as_str()),
+        ];
+        if let Some(redirect_uri) = redirect_uri { form.push(("redirect_uri", redirect_uri)); }
+
+        let mut resp = self.send(&Request::post(self.api_url(api::TOKEN), HeaderMap::new(), &form))?;
+        let token = parse_token(resp.status(), &resp.text()?)?;
+
+        Ok(self.oauth2_token(token))
+    }
+
+    /// Authorize every request with an OAuth2 token instead of OAuth1 keys.
+    pub fn oauth2_token(mut self, token: OAuth2Token) -> Self {
+        self.oauth2 = Some(Arc::new(OAuth2::new(token, self.on_token_refresh.clone())));
+        self
+    }
+
+    /// Called with the new token whenever the OAuth2 token is refreshed, e.g. to persist it.
+    pub fn on_token_refresh<F: Fn(&OAuth2Token) + Send + Sync + 'static>(mut self, on_refresh: F) -> Self {
+        self.on_token_refresh = Some(Hook::new(on_refresh));
+        match self.oauth2.take() {
+            Some(oauth2) => self.oauth2_token(oauth2.token()),
+            None => self,
+        }
+    }
+
+    /// Save every refreshed OAuth2 token to `path`, see `load_oauth2_token`, calling `on_error` when it can't be saved.
+    pub fn persist_oauth2_token<F: Fn(&std::io::Error) + Send + Sync + 'static>(self, path: &str, on_error: F) -> Self {
+        let path = path.to_owned();
+
+        self.on_token_refresh(move |token| if let Err(e) = save_token(&path, token) { on_error(&e); })
+    }
+
+    /// The current OAuth2 token, `None` when using OAuth1.
+    pub fn get_oauth2_token(&self) -> Option<OAuth2Token> { self.oauth2.as_ref().map(|oauth2| oauth2.token()) }
+
+    pub fn save_oauth2_token(&self, path: &str) -> Result<(), std::io::Error> {
+        match self.get_oauth2_token() {
+            Some(token) => save_token(path, &token),
+            None => Err(std::io::Error::other("no OAuth2 token")),
+        }
+    }
+
+    pub fn load_oauth2_token(self, path: &str) -> Result<Self, std::io::Error> {
+        let token = serde_json::from_reader(File::open(path)?)?;
+
+        Ok(self.oauth2_token(token))
+    }
+
+    pub(super) fn oauth2_token_url(&self) -> String { self.api_url(api::TOKEN) }
+
+    /// Refresh the OAuth2 token now, which otherwise happens when it expires or Tumblr answers `401`.
+    pub fn refresh_oauth2_token(&self) -> Result<OAuth2Token, TumblrClientError> { self.refresh_stale_oauth2_token(None) }
+
+    /// Refresh the OAuth2 token, unless another request already replaced the `stale` access token.
+    pub(super) fn refresh_stale_oauth2_token(&self, stale: Option<&str>) -> Result<OAuth2Token, TumblrClientError> {
+        let oauth2 = self.oauth2
+            .as_ref()
+            .ok_or_else(|| TumblrClientError::OAuth { msg: "no OAuth2 token".to_owned() })?;
+
+        oauth2.refresh(stale, || {
+            let mut resp = self.send(&oauth2.refresh_request(self.oauth2_token_url(), &self.keys)?)?;
+
+            parse_token(resp.status(), &resp.text()?)
+        })
+    }
+}
+
+fn save_token(path: &str, token: &OAuth2Token) -> Result<(), std::io::Error> {
+    serde_json::to_writer(create_private(Path::new(path))?, token)?;
+
+    Ok(())
+}
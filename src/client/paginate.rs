@@ -0,0 +1,382 @@
+// --- std ---
+use std::vec;
+// --- external ---
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::Value;
+// --- custom ---
+use super::{
+    TumblrClient, TumblrClientError, parse_json, response,
+    blog::{
+        GetBlogLikes,
+        GetBlogLikesOptionalParams,
+        GetBlogFollowing,
+        GetBlogFollowingOptionalParams,
+        GetBlogFollowers,
+        GetBlogFollowersOptionalParams,
+        GetBlogPosts,
+        GetBlogPostsOptionalParams,
+        GetBlogPostsQueue,
+        GetBlogPostsQueueOptionalParams,
+        GetBlogPostsDraft,
+        GetBlogPostsDraftOptionalParams,
+        GetBlogPostsSubmission,
+        GetBlogPostsSubmissionOptionalParams,
+    },
+    model::{Blog, Follower, Followers, Following, Likes, Post, Posts},
+    request::Request,
+    user::{
+        GetUserDashboard,
+        GetUserDashboardOptionalParams,
+        GetUserLikes,
+        GetUserLikesOptionalParams,
+        GetUserFollowing,
+        GetUserFollowingOptionalParams,
+    },
+};
+
+/// Tumblr's maximum page size, requested unless the caller sets a smaller `limit`.
+const LIMIT: u32 = 20;
+
+pub(super) struct Page<T> {
+    pub items: Vec<T>,
+    pub next: Option<u64>,
+}
+
+impl<T> Page<T> {
+    /// Offset paging, done on an empty page or once `total` (`0` if unknown) items have been seen.
+    fn by_offset(items: Vec<T>, cursor: Option<u64>, total: u64) -> Page<T> {
+        let offset = cursor.unwrap_or(0) + items.len() as u64;
+        let next = if items.is_empty() || (total != 0 && offset >= total) { None } else { Some(offset) };
+
+        Page { items, next }
+    }
+
+    /// Cursor paging on a key of the last item, such as its timestamp or id.
+    fn by_key(items: Vec<T>, key: impl Fn(&T) -> Option<u64>) -> Page<T> {
+        let next = items.last().and_then(key);
+
+        Page { items, next }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Cursor {
+    Start,
+    At(u64),
+    Done,
+}
+
+impl Cursor {
+    pub fn new(start: Option<u64>) -> Cursor { start.map_or(Cursor::Start, Cursor::At) }
+
+    /// The cursor to request the current page with, `None` once paging is over.
+    pub fn position(self) -> Option<Option<u64>> {
+        match self {
+            Cursor::Start => Some(None),
+            Cursor::At(cursor) => Some(Some(cursor)),
+            Cursor::Done => None,
+        }
+    }
+
+    /// Stops as well when a page does not move the cursor, so a misbehaving endpoint can't loop forever.
+    pub fn advance(position: Option<u64>, next: Option<u64>) -> Cursor {
+        match next {
+            Some(next) if Some(next) != position => Cursor::At(next),
+            _ => Cursor::Done,
+        }
+    }
+}
+
+type RequestFn = Box<dyn Fn(&TumblrClient, Option<u64>) -> Result<Request, TumblrClientError> + Send>;
+
+/// How to request a page at some cursor, and where the next page starts.
+pub(super) struct Pager<T> {
+    /// The cursor of the first page, such as the caller's own `offset`.
+    pub start: Option<u64>,
+    request: RequestFn,
+    pub page: fn(Value, Option<u64>) -> Result<Page<T>, TumblrClientError>,
+}
+
+impl<T> Pager<T> {
+    pub fn request(&self, client: &TumblrClient, position: Option<u64>) -> Result<Request, TumblrClientError> { (self.request)(client, position) }
+}
+
+/// Iterator over every item of a paginated endpoint, fetching pages as it goes.
+///
+/// Yields the error and stops if a page can't be fetched.
+pub struct PageIter<'a, T> {
+    client: &'a TumblrClient,
+    pager: Pager<T>,
+    cursor: Cursor,
+    items: vec::IntoIter<T>,
+}
+
+impl<'a, T> PageIter<'a, T> {
+    fn new(client: &'a TumblrClient, pager: Pager<T>) -> PageIter<'a, T> {
+        PageIter {
+            client,
+            cursor: Cursor::new(pager.start),
+            pager,
+            items: vec![].into_iter(),
+        }
+    }
+
+    fn fetch(&self, position: Option<u64>) -> Result<Page<T>, TumblrClientError> {
+        let request = self.pager.request(self.client, position)?;
+
+        (self.pager.page)(parse_json(self.client.send(&request)?)?, position)
+    }
+}
+
+impl<'a, T> Iterator for PageIter<'a, T> {
+    type Item = Result<T, TumblrClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.items.next() { return Some(Ok(item)); }
+
+            let position = self.cursor.position()?;
+            match self.fetch(position) {
+                Ok(page) => {
+                    self.cursor = Cursor::advance(position, page.next);
+                    self.items = page.items.into_iter();
+                }
+                Err(e) => {
+                    self.cursor = Cursor::Done;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl TumblrClient {
+    pub fn blog_posts_iter<'a>(&'a self, blog_identifier: &str, optional_params: Option<GetBlogPostsOptionalParams>) -> PageIter<'a, Post> { PageIter::new(self, blog_posts(blog_identifier, optional_params)) }
+
+    pub fn queue_iter<'a>(&'a self, blog_identifier: &str, optional_params: Option<GetBlogPostsQueueOptionalParams>) -> PageIter<'a, Post> { PageIter::new(self, queue(blog_identifier, optional_params)) }
+
+    pub fn drafts_iter<'a>(&'a self, blog_identifier: &str, optional_params: Option<GetBlogPostsDraftOptionalParams>) -> PageIter<'a, Post> { PageIter::new(self, drafts(blog_identifier, optional_params)) }
+
+    pub fn submissions_iter<'a>(&'a self, blog_identifier: &str, optional_params: Option<GetBlogPostsSubmissionOptionalParams>) -> PageIter<'a, Post> { PageIter::new(self, submissions(blog_identifier, optional_params)) }
+
+    pub fn blog_likes_iter<'a>(&'a self, blog_identifier: &str, optional_params: Option<GetBlogLikesOptionalParams>) -> PageIter<'a, Post> { PageIter::new(self, blog_likes(blog_identifier, optional_params)) }
+
+    pub fn blog_following_iter<'a>(&'a self, blog_identifier: &str, optional_params: Option<GetBlogFollowingOptionalParams>) -> PageIter<'a, Blog> { PageIter::new(self, blog_following(blog_identifier, optional_params)) }
+
+    pub fn followers_iter<'a>(&'a self, blog_identifier: &str, optional_params: Option<GetBlogFollowersOptionalParams>) -> PageIter<'a, Follower> { PageIter::new(self, followers(blog_identifier, optional_params)) }
+
+    pub fn dashboard_iter<'a>(&'a self, optional_params: Option<GetUserDashboardOptionalParams>) -> PageIter<'a, Post> { PageIter::new(self, dashboard(optional_params)) }
+
+    pub fn user_likes_iter<'a>(&'a self, optional_params: Option<GetUserLikesOptionalParams>) -> PageIter<'a, Post> { PageIter::new(self, user_likes(optional_params)) }
+
+    pub fn user_following_iter<'a>(&'a self, optional_params: Option<GetUserFollowingOptionalParams>) -> PageIter<'a, Blog> { PageIter::new(self, user_following(optional_params)) }
+}
+
+pub(super) fn blog_posts(blog_identifier: &str, optional_params: Option<GetBlogPostsOptionalParams>) -> Pager<Post> {
+    let blog_identifier = blog_identifier.to_owned();
+    let start = optional_params.as_ref().and_then(|params| params.offset);
+
+    Pager {
+        start,
+        request: Box::new(move |client, position| {
+            let mut params = optional_params.clone().unwrap_or_default();
+            params.limit = params.limit.or(Some(LIMIT));
+            if let Some(offset) = position { params = params.offset(offset); }
+
+            client.endpoint_request(GetBlogPosts { blog_identifier: blog_identifier.clone(), optional_params: Some(params) })
+        }),
+        page: |value, position| {
+            let posts: Posts = response(value)?;
+            Ok(Page::by_offset(posts.posts, position, posts.total_posts.unwrap_or(0)))
+        },
+    }
+}
+
+pub(super) fn queue(blog_identifier: &str, optional_params: Option<GetBlogPostsQueueOptionalParams>) -> Pager<Post> {
+    let blog_identifier = blog_identifier.to_owned();
+    let start = optional_params.as_ref().and_then(|params| params.offset);
+
+    Pager {
+        start,
+        request: Box::new(move |client, position| {
+            let mut params = optional_params.clone().unwrap_or_default();
+            params.limit = params.limit.or(Some(LIMIT));
+            if let Some(offset) = position { params = params.offset(offset); }
+
+            client.endpoint_request(GetBlogPostsQueue { blog_identifier: blog_identifier.clone(), optional_params: Some(params) })
+        }),
+        page: |value, position| {
+            let posts: Posts = response(value)?;
+            Ok(Page::by_offset(posts.posts, position, 0))
+        },
+    }
+}
+
+pub(super) fn drafts(blog_identifier: &str, optional_params: Option<GetBlogPostsDraftOptionalParams>) -> Pager<Post> {
+    let blog_identifier = blog_identifier.to_owned();
+
+    Pager {
+        start: None,
+        request: Box::new(move |client, position| {
+            let mut params = optional_params.clone().unwrap_or_default();
+            if let Some(before_id) = position { params = params.before_id(before_id); }
+
+            client.endpoint_request(GetBlogPostsDraft { blog_identifier: blog_identifier.clone(), optional_params: Some(params) })
+        }),
+        page: |value, _| {
+            let posts: Posts = response(value)?;
+            Ok(Page::by_key(posts.posts, |post| Some(post.id)))
+        },
+    }
+}
+
+pub(super) fn submissions(blog_identifier: &str, optional_params: Option<GetBlogPostsSubmissionOptionalParams>) -> Pager<Post> {
+    let blog_identifier = blog_identifier.to_owned();
+    let start = optional_params.as_ref().and_then(|params| params.offset);
+
+    Pager {
+        start,
+        request: Box::new(move |client, position| {
+            let mut params = optional_params.clone().unwrap_or_default();
+            if let Some(offset) = position { params = params.offset(offset); }
+
+            client.endpoint_request(GetBlogPostsSubmission { blog_identifier: blog_identifier.clone(), optional_params: Some(params) })
+        }),
+        page: |value, position| {
+            let posts: Posts = response(value)?;
+            Ok(Page::by_offset(posts.posts, position, 0))
+        },
+    }
+}
+
+pub(super) fn blog_likes(blog_identifier: &str, optional_params: Option<GetBlogLikesOptionalParams>) -> Pager<Post> {
+    let blog_identifier = blog_identifier.to_owned();
+
+    let forward = optional_params.as_ref().is_some_and(|params| params.after.is_some());
+
+    Pager {
+        start: None,
+        request: Box::new(move |client, position| {
+            let mut params = optional_params.clone().unwrap_or_default();
+            params.limit = params.limit.or(Some(LIMIT));
+            if let Some(liked) = position.and_then(liked_at) {
+                params.offset = None;
+                if forward { params.after = Some(liked); } else { params.before = Some(liked); }
+            }
+
+            client.endpoint_request(GetBlogLikes { blog_identifier: blog_identifier.clone(), optional_params: Some(params) })
+        }),
+        page: if forward { |value, _| likes_page(value, true) } else { |value, _| likes_page(value, false) },
+    }
+}
+
+pub(super) fn blog_following(blog_identifier: &str, optional_params: Option<GetBlogFollowingOptionalParams>) -> Pager<Blog> {
+    let blog_identifier = blog_identifier.to_owned();
+    let start = optional_params.as_ref().and_then(|params| params.offset);
+
+    Pager {
+        start,
+        request: Box::new(move |client, position| {
+            let mut params = optional_params.clone().unwrap_or_default();
+            params.limit = params.limit.or(Some(LIMIT));
+            if let Some(offset) = position { params = params.offset(offset); }
+
+            client.endpoint_request(GetBlogFollowing { blog_identifier: blog_identifier.clone(), optional_params: Some(params) })
+        }),
+        page: |value, position| {
+            let following: Following = response(value)?;
+            Ok(Page::by_offset(following.blogs, position, following.total_blogs))
+        },
+    }
+}
+
+pub(super) fn followers(blog_identifier: &str, optional_params: Option<GetBlogFollowersOptionalParams>) -> Pager<Follower> {
+    let blog_identifier = blog_identifier.to_owned();
+    let start = optional_params.as_ref().and_then(|params| params.offset);
+
+    Pager {
+        start,
+        request: Box::new(move |client, position| {
+            let mut params = optional_params.clone().unwrap_or_default();
+            params.limit = params.limit.or(Some(LIMIT));
+            if let Some(offset) = position { params = params.offset(offset); }
+
+            client.endpoint_request(GetBlogFollowers { blog_identifier: blog_identifier.clone(), optional_params: Some(params) })
+        }),
+        page: |value, position| {
+            let followers: Followers = response(value)?;
+            Ok(Page::by_offset(followers.users, position, followers.total_users))
+        },
+    }
+}
+
+pub(super) fn dashboard(optional_params: Option<GetUserDashboardOptionalParams>) -> Pager<Post> {
+    let start = optional_params.as_ref().and_then(|params| params.offset);
+
+    Pager {
+        start,
+        request: Box::new(move |client, position| {
+            let mut params = optional_params.clone().unwrap_or_default();
+            params.limit = params.limit.or(Some(LIMIT));
+            if let Some(offset) = position { params = params.offset(offset); }
+
+            client.endpoint_request(GetUserDashboard { optional_params: Some(params) })
+        }),
+        page: |value, position| {
+            let posts: Posts = response(value)?;
+            Ok(Page::by_offset(posts.posts, position, 0))
+        },
+    }
+}
+
+pub(super) fn user_likes(optional_params: Option<GetUserLikesOptionalParams>) -> Pager<Post> {
+    let forward = optional_params.as_ref().is_some_and(|params| params.after.is_some());
+
+    Pager {
+        start: None,
+        request: Box::new(move |client, position| {
+            let mut params = optional_params.clone().unwrap_or_default();
+            params.limit = params.limit.or(Some(LIMIT));
+            if let Some(liked) = position.and_then(liked_at) {
+                params.offset = None;
+                if forward { params.after = Some(liked); } else { params.before = Some(liked); }
+            }
+
+            client.endpoint_request(GetUserLikes { optional_params: Some(params) })
+        }),
+        page: if forward { |value, _| likes_page(value, true) } else { |value, _| likes_page(value, false) },
+    }
+}
+
+pub(super) fn user_following(optional_params: Option<GetUserFollowingOptionalParams>) -> Pager<Blog> {
+    let start = optional_params.as_ref().and_then(|params| params.offset);
+
+    Pager {
+        start,
+        request: Box::new(move |client, position| {
+            let mut params = optional_params.clone().unwrap_or_default();
+            params.limit = params.limit.or(Some(LIMIT));
+            if let Some(offset) = position { params = params.offset(offset); }
+
+            client.endpoint_request(GetUserFollowing { optional_params: Some(params) })
+        }),
+        page: |value, position| {
+            let following: Following = response(value)?;
+            Ok(Page::by_offset(following.blogs, position, following.total_blogs))
+        },
+    }
+}
+
+/// A page of likes, continuing before the oldest like or, when paging forward by `after`, after the latest one.
+///
+/// The next page is requested by `before` or `after` alone, as Tumblr rejects them alongside `offset`.
+fn likes_page(value: Value, forward: bool) -> Result<Page<Post>, TumblrClientError> {
+    let likes: Likes = response(value)?;
+    let timestamps = likes.liked_posts.iter().filter_map(|post| post.liked_timestamp);
+    let next = if forward { timestamps.max() } else { timestamps.min() };
+
+    Ok(Page { items: likes.liked_posts, next })
+}
+
+/// The `before` or `after` of the next page of likes, from the timestamp of a like.
+fn liked_at(timestamp: u64) -> Option<DateTime<Utc>> { Utc.timestamp_opt(timestamp as i64, 0).single() }
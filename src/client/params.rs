@@ -0,0 +1,209 @@
+// --- external ---
+use chrono::{DateTime, SecondsFormat, Utc};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use ring::hmac;
+// --- custom ---
+use super::TumblrClientError;
+
+/// Everything but the unreserved characters of RFC 3986, the set OAuth 1.0 encodes its base string with.
+///
+/// Urls and form bodies are encoded the same way, so Tumblr decodes exactly the params which were signed.
+const RFC3986: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+pub(super) fn encode(s: &str) -> String { utf8_percent_encode(s, RFC3986).to_string() }
+
+/// `k=v&k=v`, names and values encoded, for a query or a form body.
+pub(super) fn encode_pairs<K: AsRef<str>, V: AsRef<str>>(params: &[(K, V)]) -> String {
+    params.iter()
+        .map(|(k, v)| format!("{}={}", encode(k.as_ref()), encode(v.as_ref())))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+pub(super) fn build_query(api: &str, params: &[(&str, &str)]) -> String {
+    if params.is_empty() { api.to_owned() } else { format!("{}?{}", api, encode_pairs(params)) }
+}
+
+/// The params to sign, encoded and sorted by name then value as RFC 5849, section 3.4.1.3.2, requires.
+///
+/// Repeated names, such as several `tags[]`, are all kept since the request sends all of them.
+pub(super) fn build_params(params: Vec<(&str, &str)>) -> Vec<(String, String)> {
+    let mut params = params.into_iter()
+        .map(|(k, v)| (encode(k), encode(v)))
+        .collect::<Vec<_>>();
+    params.sort_unstable();
+
+    params
+}
+
+/// `METHOD&uri&params` of RFC 5849, section 3.4.1, `params` as `build_params` returns them.
+fn base_string(method: &str, uri: &str, params: &[(String, String)]) -> String {
+    let params = params.iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    format!("{}&{}&{}", encode(method), encode(uri), encode(&params))
+}
+
+/// The `HMAC-SHA1` signature of the base string, `params` including the `oauth_*` ones.
+pub(super) fn signature(method: &str, uri: &str, params: &[(String, String)], consumer_secret: &str, token_secret: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, format!("{}&{}", encode(consumer_secret), encode(token_secret)).as_bytes());
+
+    base64::encode(hmac::sign(&key, base_string(method, uri, params).as_bytes()).as_ref())
+}
+
+/// Seconds since the epoch, as the `before` and `after` of posts and likes.
+pub(super) fn timestamp(time: &DateTime<Utc>) -> String { time.timestamp().to_string() }
+
+/// ISO 8601, as the dates of NPF posts.
+pub(super) fn iso8601(time: &DateTime<Utc>) -> String { time.to_rfc3339_opts(SecondsFormat::Secs, true) }
+
+/// `2019-01-02 03:04:05 GMT`, as the `date` of legacy posts.
+pub(super) fn gmt(time: &DateTime<Utc>) -> String { time.format("%Y-%m-%d %H:%M:%S GMT").to_string() }
+
+/// Tags as Tumblr takes them, comma separated.
+pub(super) fn join_tags(tags: &[String]) -> String { tags.join(",") }
+
+pub(super) fn check_limit(limit: Option<u32>) -> Result<(), TumblrClientError> {
+    match limit {
+        Some(limit) if !(1..=20).contains(&limit) => Err(invalid(format!("limit must be between 1 and 20, got {}", limit))),
+        _ => Ok(()),
+    }
+}
+
+/// Tags are sent comma separated, so none may hold a comma.
+pub(super) fn check_tags(tags: Option<&[String]>) -> Result<(), TumblrClientError> {
+    match tags.and_then(|tags| tags.iter().find(|tag| tag.contains(','))) {
+        Some(tag) => Err(invalid(format!("tags can't contain a comma, got {:?}", tag))),
+        None => Ok(()),
+    }
+}
+
+/// Fail if more than one of the named params is set.
+pub(super) fn check_exclusive(params: &[(&str, bool)]) -> Result<(), TumblrClientError> {
+    let set = params.iter()
+        .filter(|&&(_, set)| set)
+        .map(|&(name, _)| name)
+        .collect::<Vec<_>>();
+    if set.len() > 1 { Err(invalid(format!("only one of [{}] can be set", set.join(", ")))) } else { Ok(()) }
+}
+
+fn invalid(msg: String) -> TumblrClientError { TumblrClientError::InvalidInput { msg } }
+
+#[cfg(test)]
+mod tests {
+    // --- custom ---
+    use super::*;
+
+    #[test]
+    fn encode_vectors() {
+        for &(raw, encoded) in &[
+            ("abcXYZ019", "abcXYZ019"),
+            ("-._~", "-._~"),
+            ("my art", "my%20art"),
+            ("c++", "c%2B%2B"),
+            ("a&b=c", "a%26b%3Dc"),
+            ("50%", "50%25"),
+            ("a/b?c#d", "a%2Fb%3Fc%23d"),
+            ("*!'()", "%2A%21%27%28%29"),
+            ("café", "caf%C3%A9"),
+            ("日本", "%E6%97%A5%E6%9C%AC"),
+            ("🐱", "%F0%9F%90%B1"),
+        ] {
+            assert_eq!(encode(raw), encoded, "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn query() {
+        assert_eq!(build_query("https://api.tumblr.com/v2/user/likes", &[]), "https://api.tumblr.com/v2/user/likes");
+        assert_eq!(
+            build_query("https://api.tumblr.com/v2/blog/staff/posts", &[("tag", "my art"), ("tag", "c++"), ("a=b", "&")]),
+            "https://api.tumblr.com/v2/blog/staff/posts?tag=my%20art&tag=c%2B%2B&a%3Db=%26",
+        );
+    }
+
+    /// The signed params must be encoded exactly as they are in the url.
+    #[test]
+    fn signed_params_match_url() {
+        let params = vec![("tag", "my art c++ & = café 🐱"), ("limit", "20")];
+        let query = encode_pairs(&params);
+
+        let mut sorted = query.split('&').collect::<Vec<_>>();
+        sorted.sort_unstable();
+        let signed = build_params(params).iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>();
+        assert_eq!(signed, sorted);
+    }
+
+    /// The example of RFC 5849, section 3.4.1.3, where `a3` is repeated.
+    #[test]
+    fn rfc5849_params() {
+        let params = vec![("b5", "=%3D"), ("a3", "a"), ("c@", ""), ("a2", "r b"), ("c2", ""), ("a3", "2 q")];
+        assert_eq!(encode_pairs(&params), "b5=%3D%253D&a3=a&c%40=&a2=r%20b&c2=&a3=2%20q");
+
+        let mut params = params;
+        params.extend(vec![
+            ("oauth_consumer_key", "9djdj82h48djs9d2"),
+            ("oauth_token", "kkk9d7dh3k39sjv7"),
+            ("oauth_signature_method", "HMAC-SHA1"),
+            ("oauth_timestamp", "137131201"),
+            ("oauth_nonce", "7d8f3e4a"),
+        ]);
+        assert_eq!(
+            base_string("POST", "http://example.com/request", &build_params(params)),
+            "POST&http%3A%2F%2Fexample.com%2Frequest&a2%3Dr%2520b%26a3%3D2%2520q%26a3%3Da%26b5%3D%253D%25253D%26c%2540%3D%26c2%3D%26\
+             oauth_consumer_key%3D9djdj82h48djs9d2%26oauth_nonce%3D7d8f3e4a%26oauth_signature_method%3DHMAC-SHA1%26\
+             oauth_timestamp%3D137131201%26oauth_token%3Dkkk9d7dh3k39sjv7",
+        );
+    }
+
+    /// The example of the OAuth Core 1.0 specification, appendix A.5.
+    #[test]
+    fn hmac_sha1() {
+        let params = build_params(vec![
+            ("file", "vacation.jpg"),
+            ("size", "original"),
+            ("oauth_consumer_key", "dpf43f3p2l4k3l03"),
+            ("oauth_token", "nnch734d00sl2jdk"),
+            ("oauth_nonce", "kllo9940pd9333jh"),
+            ("oauth_timestamp", "1191242096"),
+            ("oauth_signature_method", "HMAC-SHA1"),
+            ("oauth_version", "1.0"),
+        ]);
+        assert_eq!(signature("GET", "http://photos.example.net/photos", &params, "kd94hf93k423kf44", "pfkkdhi9sl3r4s00"), "tR3+Ty81lMeYAr/Fid0kMTYa/WM=");
+    }
+
+    #[test]
+    fn repeated_names_are_signed() {
+        let sign = |params| signature("POST", "https://api.tumblr.com/v2/blog/rumblr/post", &build_params(params), "secret", "");
+
+        let both = sign(vec![("tags[]", "b"), ("type", "text"), ("tags[]", "a")]);
+        assert_eq!(both, sign(vec![("tags[]", "a"), ("tags[]", "b"), ("type", "text")]));
+        assert_ne!(both, sign(vec![("tags[]", "a"), ("type", "text")]));
+        assert_ne!(both, sign(vec![("tags[]", "b"), ("type", "text")]));
+    }
+
+    #[test]
+    fn typed_values() {
+        // --- external ---
+        use chrono::TimeZone;
+
+        let time = Utc.with_ymd_and_hms(2019, 1, 2, 3, 4, 5).unwrap();
+        assert_eq!(timestamp(&time), "1546398245");
+        assert_eq!(iso8601(&time), "2019-01-02T03:04:05Z");
+        assert_eq!(gmt(&time), "2019-01-02 03:04:05 GMT");
+        assert_eq!(join_tags(&["my art".to_owned(), "c++".to_owned()]), "my art,c++");
+
+        assert!(check_limit(Some(20)).is_ok());
+        assert!(check_limit(Some(0)).is_err());
+        assert!(check_limit(Some(21)).is_err());
+        assert!(check_tags(Some(&["a,b".to_owned()])).is_err());
+        assert!(check_exclusive(&[("offset", false), ("before", true)]).is_ok());
+        assert!(check_exclusive(&[("offset", true), ("before", true)]).is_err());
+    }
+}
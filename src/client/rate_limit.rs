@@ -0,0 +1,223 @@
+// --- std ---
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+// --- external ---
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+// --- custom ---
+use super::TumblrClientError;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Quota {
+    pub limit: u64,
+    pub remaining: u64,
+    resets_at: Instant,
+}
+
+impl Quota {
+    fn from_headers(headers: &HeaderMap, period: &str) -> Option<Quota> {
+        let header = |name: &str| headers.get(format!("x-ratelimit-{}-{}", period, name).as_str())
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok());
+
+        Some(Quota {
+            limit: header("limit")?,
+            remaining: header("remaining")?,
+            resets_at: Instant::now() + Duration::from_secs(header("reset").unwrap_or(0)),
+        })
+    }
+
+    pub fn resets_in(&self) -> Duration { self.resets_at.saturating_duration_since(Instant::now()) }
+
+    fn exhausted(&self) -> bool { self.remaining == 0 && self.resets_in() > Duration::from_secs(0) }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateLimit {
+    pub per_day: Option<Quota>,
+    pub per_hour: Option<Quota>,
+}
+
+impl RateLimit {
+    pub(super) fn from_headers(headers: &HeaderMap) -> Option<RateLimit> {
+        let rate_limit = RateLimit {
+            per_day: Quota::from_headers(headers, "perday"),
+            per_hour: Quota::from_headers(headers, "perhour"),
+        };
+
+        if rate_limit.per_day.is_none() && rate_limit.per_hour.is_none() { None } else { Some(rate_limit) }
+    }
+
+    /// How long until every exhausted quota has been reset.
+    pub fn wait(&self) -> Option<Duration> {
+        [self.per_day, self.per_hour].iter()
+            .flatten()
+            .filter(|quota| quota.exhausted())
+            .map(Quota::resets_in)
+            .max()
+    }
+}
+
+/// A token bucket which paces requests to `requests` per `period`, allowing bursts of up to `requests`.
+#[derive(Debug)]
+pub struct Throttle {
+    capacity: f64,
+    per_second: f64,
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl Throttle {
+    pub fn new(requests: u32, period: Duration) -> Throttle {
+        let capacity = f64::from(requests.max(1));
+
+        Throttle {
+            capacity,
+            per_second: capacity / period.as_secs_f64().max(f64::EPSILON),
+            bucket: Mutex::new((capacity, Instant::now())),
+        }
+    }
+
+    /// Take a token, returning how long the caller must wait before using it.
+    pub(super) fn acquire(&self) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        let (ref mut tokens, ref mut refilled) = *bucket;
+
+        let now = Instant::now();
+        *tokens = (*tokens + now.duration_since(*refilled).as_secs_f64() * self.per_second).min(self.capacity) - 1.;
+        *refilled = now;
+
+        if *tokens >= 0. { Duration::from_secs(0) } else { Duration::from_secs_f64(-*tokens / self.per_second) }
+    }
+
+    /// Give back a token taken by `acquire` for a request which was not sent.
+    fn release(&self) { self.bucket.lock().unwrap_or_else(|e| e.into_inner()).0 += 1.; }
+}
+
+/// Rate-limit state shared by every clone of a client, keyed by consumer key.
+#[derive(Debug, Clone, Default)]
+pub(super) struct RateLimiter {
+    pub throttle: Option<Arc<Throttle>>,
+    rate_limits: Arc<Mutex<HashMap<String, RateLimit>>>,
+}
+
+impl RateLimiter {
+    pub fn get(&self, consumer_key: &str) -> Option<RateLimit> {
+        self.rate_limits.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(consumer_key)
+            .cloned()
+    }
+
+    /// How long to wait before the next request, if throttling is enabled or a quota of `consumer_key` is exhausted.
+    ///
+    /// Fails with `RateLimited` instead if the wait would end after `deadline`, that of the `RetryPolicy`.
+    pub fn wait(&self, consumer_key: &str, deadline: Option<Instant>) -> Result<Option<Duration>, TumblrClientError> {
+        let quota = self.get(consumer_key).and_then(|rate_limit| rate_limit.wait());
+        let wait = match quota.max(self.throttle.as_ref().map(|throttle| throttle.acquire())) {
+            Some(wait) => wait,
+            None => return Ok(None),
+        };
+        if deadline.is_some_and(|deadline| Instant::now() + wait > deadline) {
+            if let Some(ref throttle) = self.throttle { throttle.release(); }
+
+            return Err(TumblrClientError::RateLimited { wait });
+        }
+
+        Ok(Some(wait))
+    }
+
+    pub fn record(&self, consumer_key: &str, headers: &HeaderMap) {
+        if let Some(rate_limit) = RateLimit::from_headers(headers) {
+            self.rate_limits.lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(consumer_key.to_owned(), rate_limit);
+        }
+    }
+}
+
+/// `Retry-After` as either delta-seconds or an HTTP-date, which is `0` once past.
+pub(super) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() { return Some(Duration::from_secs(seconds)); }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((date - Utc::now()).to_std().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    // --- external ---
+    use reqwest::header::HeaderValue;
+    // --- custom ---
+    use super::*;
+
+    fn retry_after_value(value: &str) -> Option<Duration> {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(value).unwrap());
+
+        retry_after(&headers)
+    }
+
+    #[test]
+    fn retry_after_forms() {
+        assert_eq!(retry_after_value("120"), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after_value(" 0 "), Some(Duration::from_secs(0)));
+
+        let in_a_minute = (Utc::now() + chrono::Duration::seconds(60)).format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        let wait = retry_after_value(&in_a_minute).unwrap();
+        assert!(wait > Duration::from_secs(55) && wait <= Duration::from_secs(60), "{:?}", wait);
+        assert_eq!(retry_after_value("Sun, 06 Nov 1994 08:49:37 GMT"), Some(Duration::from_secs(0)));
+
+        assert_eq!(retry_after_value("soon"), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn wait_within_deadline() {
+        let rate_limiter = RateLimiter { throttle: Some(Arc::new(Throttle::new(1, Duration::from_secs(3600)))), ..RateLimiter::default() };
+        assert_eq!(rate_limiter.wait("key", None).unwrap(), Some(Duration::from_secs(0)));
+
+        match rate_limiter.wait("key", Some(Instant::now() + Duration::from_secs(1))) {
+            Err(TumblrClientError::RateLimited { wait }) => assert!(wait > Duration::from_secs(3500), "{:?}", wait),
+            wait => panic!("waits past the deadline: {:?}", wait),
+        }
+        assert!(RateLimiter::default().wait("key", Some(Instant::now())).unwrap().is_none());
+    }
+
+    #[test]
+    fn rate_limited_keeps_the_token() {
+        let throttle = Arc::new(Throttle::new(1, Duration::from_secs(3600)));
+        let rate_limiter = RateLimiter { throttle: Some(throttle.clone()), ..RateLimiter::default() };
+        let tokens = || throttle.bucket.lock().unwrap().0;
+        rate_limiter.wait("key", None).unwrap();
+        let before = tokens();
+
+        let deadline = Some(Instant::now() + Duration::from_secs(1));
+        for _ in 0..2 {
+            match rate_limiter.wait("key", deadline) {
+                Err(TumblrClientError::RateLimited { wait }) => assert!(wait <= Duration::from_secs(3600), "{:?}", wait),
+                wait => panic!("waits past the deadline: {:?}", wait),
+            }
+            assert!(tokens() - before < 0.01, "{} then {}", before, tokens());
+            assert!(tokens() - before >= 0., "{} then {}", before, tokens());
+        }
+    }
+
+    #[test]
+    fn exhausted_quota_without_throttle() {
+        let rate_limiter = RateLimiter::default();
+        let mut headers = HeaderMap::new();
+        for &(name, value) in &[("x-ratelimit-perhour-limit", "1000"), ("x-ratelimit-perhour-remaining", "0"), ("x-ratelimit-perhour-reset", "600")] {
+            headers.insert(name, HeaderValue::from_static(value));
+        }
+        rate_limiter.record("key", &headers);
+
+        let wait = rate_limiter.wait("key", None).unwrap().unwrap();
+        assert!(wait > Duration::from_secs(590) && wait <= Duration::from_secs(600), "{:?}", wait);
+        assert!(matches!(rate_limiter.wait("key", Some(Instant::now() + Duration::from_secs(1))), Err(TumblrClientError::RateLimited { .. })));
+        assert!(rate_limiter.wait("other key", None).unwrap().is_none());
+    }
+}
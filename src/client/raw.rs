@@ -0,0 +1,126 @@
+// --- external ---
+use reqwest::{
+    Method, Response,
+    header::{CONTENT_TYPE, HeaderMap, HeaderValue},
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+// --- custom ---
+use super::{
+    TumblrClient, TumblrClientError, parse_json, response,
+    blog::Media,
+    params::build_query,
+    request::{Body, Part, PartData, Request},
+};
+
+/// How a request proves who sends it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Auth {
+    /// Nothing, for public endpoints such as avatars.
+    None,
+    /// The consumer key, as the `api_key` param.
+    ApiKey,
+    /// Signed with the OAuth1 keys, or carrying the OAuth2 token.
+    OAuth,
+}
+
+/// The body of a request sent with `TumblrClient::request`.
+pub enum RequestBody {
+    Empty,
+    /// Form encoded, and covered by the OAuth signature.
+    Form(Vec<(String, String)>),
+    Json(Value),
+    /// Of its fields, only the `Text` ones are covered by the OAuth signature, as for legacy photo uploads.
+    Multipart(Vec<(String, FormField)>),
+}
+
+/// A field of a multipart body.
+pub enum FormField {
+    Text(String),
+    /// Sent as `application/json`, e.g. the `json` part of an NPF post with uploads.
+    Json(Value),
+    Media(Media),
+    /// Path of a file, read whenever the request is sent.
+    File(String),
+}
+
+impl RequestBody {
+    pub fn form(form: &[(&str, &str)]) -> RequestBody {
+        RequestBody::Form(form.iter()
+            .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+            .collect())
+    }
+}
+
+impl TumblrClient {
+    /// Call an endpoint rumblr doesn't wrap, e.g. `request(Method::GET, "blog/staff/posts", &[("tag", "art")], RequestBody::Empty)`.
+    ///
+    /// `path` is relative to the API base url unless it is a full url. The request is signed with OAuth when the client
+    /// holds user credentials and carries the `api_key` otherwise. The response is returned whatever its status.
+    pub fn request(&self, method: Method, path: &str, params: &[(&str, &str)], body: RequestBody) -> Result<Response, TumblrClientError> {
+        self.request_with(self.default_auth(), method, path, params, body)
+    }
+
+    /// Like `request`, authorized with `auth`.
+    pub fn request_with(&self, auth: Auth, method: Method, path: &str, params: &[(&str, &str)], body: RequestBody) -> Result<Response, TumblrClientError> {
+        self.send(&self.raw_request(auth, method, path, params, body)?)
+    }
+
+    /// Like `request`, with error statuses turned into errors and the `response` of Tumblr's envelope deserialized.
+    pub fn request_json<T: DeserializeOwned>(&self, method: Method, path: &str, params: &[(&str, &str)], body: RequestBody) -> Result<T, TumblrClientError> {
+        response(parse_json(self.request(method, path, params, body)?)?)
+    }
+
+    pub(super) fn default_auth(&self) -> Auth {
+        if self.oauth2.is_some() || !self.keys.access_key.is_empty() { Auth::OAuth } else { Auth::ApiKey }
+    }
+
+    pub(super) fn raw_request(&self, auth: Auth, method: Method, path: &str, params: &[(&str, &str)], body: RequestBody) -> Result<Request, TumblrClientError> {
+        let api = if path.starts_with("http://") || path.starts_with("https://") { path.to_owned() } else { self.api_url(path.trim_start_matches('/')) };
+        let mut query = params.to_vec();
+        if auth == Auth::ApiKey { query.insert(0, ("api_key", &self.keys.consumer_key)); }
+
+        let body = match body {
+            RequestBody::Empty => Body::Empty,
+            RequestBody::Form(form) => Body::Form(form),
+            RequestBody::Json(json) => Body::Json(json.to_string()),
+            RequestBody::Multipart(fields) => Body::Multipart(fields.into_iter()
+                .map(|(name, field)| match field {
+                    FormField::Text(text) => Ok(Part::new(name, PartData::Text(text))),
+                    FormField::Json(json) => {
+                        let mut part = Part::new(name, PartData::Text(json.to_string()));
+                        part.mime_type = Some("application/json".to_owned());
+
+                        Ok(part)
+                    }
+                    FormField::Media(media) => media.into_part(name),
+                    FormField::File(path) => Ok(Part::new(name, PartData::File(path))),
+                })
+                .collect::<Result<_, _>>()?),
+        };
+        let mut headers = match auth {
+            Auth::OAuth => {
+                let mut signed = query.clone();
+                match body {
+                    Body::Form(ref form) => signed.extend(form.iter().map(|(k, v)| (k.as_str(), v.as_str()))),
+                    Body::Multipart(ref parts) => signed.extend(parts.iter().filter_map(|part| match part.data {
+                        PartData::Text(ref text) if part.mime_type.is_none() => Some((part.name.as_str(), text.as_str())),
+                        _ => None,
+                    })),
+                    Body::Empty | Body::Json(_) => {}
+                }
+
+                self.auth_headers(method.as_str(), &api, signed)?
+            }
+            Auth::None | Auth::ApiKey => HeaderMap::new(),
+        };
+        if let Body::Json(_) = body { headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json")); }
+
+        Ok(Request {
+            method,
+            url: build_query(&api, &query),
+            headers,
+            body,
+        })
+    }
+}
@@ -0,0 +1,154 @@
+// --- external ---
+use reqwest::{
+    Client, Method, RequestBuilder,
+    header::{CONTENT_TYPE, HeaderMap, HeaderValue},
+    multipart::{self, Form},
+};
+// --- custom ---
+use super::{TumblrClientError, params::encode_pairs};
+
+const FORM: &str = "application/x-www-form-urlencoded";
+
+#[derive(Debug, Clone)]
+pub(crate) enum Body {
+    Empty,
+    Form(Vec<(String, String)>),
+    Json(String),
+    Multipart(Vec<Part>),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum PartData {
+    Text(String),
+    Bytes(Vec<u8>),
+    /// Path of a file, read whenever the request is sent.
+    File(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Part {
+    pub name: String,
+    pub data: PartData,
+    pub mime_type: Option<String>,
+    pub file_name: Option<String>,
+}
+
+impl Part {
+    pub fn new(name: String, data: PartData) -> Part {
+        Part {
+            name,
+            data,
+            mime_type: None,
+            file_name: None,
+        }
+    }
+}
+
+/// A fully signed request, independent of the session which sends it.
+#[derive(Debug, Clone)]
+pub(crate) struct Request {
+    pub method: Method,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Body,
+}
+
+impl Request {
+    pub fn get(url: String, headers: Option<HeaderMap>) -> Request {
+        Request {
+            method: Method::GET,
+            url,
+            headers: headers.unwrap_or_default(),
+            body: Body::Empty,
+        }
+    }
+
+    pub fn post(url: String, headers: HeaderMap, form: &[(&str, &str)]) -> Request {
+        Request {
+            method: Method::POST,
+            url,
+            headers,
+            body: Body::Form(to_owned_pairs(form)),
+        }
+    }
+
+    /// Whether sending the request twice has the effect of sending it once, as RFC 7231, section 4.2.2, defines for these methods.
+    pub fn idempotent(&self) -> bool { matches!(self.method, Method::GET | Method::HEAD | Method::PUT | Method::DELETE) }
+
+    pub fn build(&self, session: &Client) -> Result<RequestBuilder, TumblrClientError> {
+        let request = session.request(self.method.clone(), &self.url).headers(self.headers.clone());
+
+        Ok(match self.body {
+            Body::Empty => request,
+            Body::Form(ref form) => request
+                .header(CONTENT_TYPE, HeaderValue::from_static(FORM))
+                .body(encode_pairs(form)),
+            Body::Json(ref json) => request.body(json.to_owned()),
+            Body::Multipart(ref parts) => {
+                let mut form = Form::new();
+                for part in parts {
+                    let mut form_part = match part.data {
+                        PartData::Text(ref text) => multipart::Part::text(text.to_owned()),
+                        PartData::Bytes(ref bytes) => multipart::Part::bytes(bytes.to_owned()),
+                        PartData::File(ref f) => multipart::Part::file(f).map_err(|e| invalid_file(f, e))?,
+                    };
+                    if let Some(ref mime_type) = part.mime_type { form_part = form_part.mime_str(mime_type)?; }
+                    if let Some(ref file_name) = part.file_name { form_part = form_part.file_name(file_name.to_owned()); }
+
+                    form = form.part(part.name.to_owned(), form_part);
+                }
+
+                request.multipart(form)
+            }
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+impl Request {
+    pub fn build_async(&self, session: &reqwest::r#async::Client) -> Result<reqwest::r#async::RequestBuilder, TumblrClientError> {
+        // --- external ---
+        use reqwest::r#async::multipart::{Form, Part};
+
+        let request = session.request(self.method.clone(), &self.url).headers(self.headers.clone());
+
+        Ok(match self.body {
+            Body::Empty => request,
+            Body::Form(ref form) => request
+                .header(CONTENT_TYPE, HeaderValue::from_static(FORM))
+                .body(encode_pairs(form)),
+            Body::Json(ref json) => request.body(json.to_owned()),
+            Body::Multipart(ref parts) => {
+                let mut form = Form::new();
+                for part in parts {
+                    let mut form_part = match part.data {
+                        PartData::Text(ref text) => Part::text(text.to_owned()),
+                        PartData::Bytes(ref bytes) => Part::bytes(bytes.to_owned()),
+                        PartData::File(ref f) => {
+                            let file_name = std::path::Path::new(f)
+                                .file_name()
+                                .map(|file_name| file_name.to_string_lossy().into_owned());
+
+                            let form_part = Part::bytes(std::fs::read(f).map_err(|e| invalid_file(f, e))?);
+                            if let Some(file_name) = file_name { form_part.file_name(file_name) } else { form_part }
+                        }
+                    };
+                    if let Some(ref mime_type) = part.mime_type { form_part = form_part.mime_str(mime_type)?; }
+                    if let Some(ref file_name) = part.file_name { form_part = form_part.file_name(file_name.to_owned()); }
+
+                    form = form.part(part.name.to_owned(), form_part);
+                }
+
+                request.multipart(form)
+            }
+        })
+    }
+}
+
+fn invalid_file(path: &str, e: std::io::Error) -> TumblrClientError { TumblrClientError::InvalidInput { msg: format!("{}: {}", path, e) } }
+
+fn to_owned_pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs.iter()
+        .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+        .collect()
+}
@@ -0,0 +1,263 @@
+// --- std ---
+use std::time::{Duration, Instant};
+// --- external ---
+use rand::Rng;
+use reqwest::{Response, StatusCode};
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    deadline: Option<Duration>,
+    jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            deadline: None,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> RetryPolicy { RetryPolicy::default() }
+
+    /// Send every request exactly once.
+    pub fn never() -> RetryPolicy { RetryPolicy::default().max_attempts(1) }
+
+    /// Total number of attempts, including the first one. `0` is treated as `1`.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Delay before the first retry, doubled on every following retry.
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Give up once a retry would end after `deadline` measured from the first attempt.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    fn delay(&self, retry: u32) -> Duration {
+        let delay = self.base_delay
+            .checked_mul(2u32.saturating_pow(retry))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        if self.jitter {
+            let millis = delay.as_millis() as u64;
+            Duration::from_millis(rand::thread_rng().gen_range(millis / 2, millis + 1))
+        } else { delay }
+    }
+
+    /// The policy applying to a request, given the client's `WritePolicy`.
+    pub(super) fn for_request(&self, write_policy: &WritePolicy, idempotent: bool) -> RetryPolicy {
+        match write_policy {
+            WritePolicy::Never if !idempotent => RetryPolicy::never(),
+            _ => self.clone(),
+        }
+    }
+
+    /// When retries of a request first attempted at `started` must have ended.
+    pub(super) fn deadline_at(&self, started: Instant) -> Option<Instant> { self.deadline.map(|deadline| started + deadline) }
+
+    /// How long to wait before attempt number `attempt + 1`, or `None` when the policy is exhausted.
+    /// `retry_after` is the server's `Retry-After`, which the delay never undercuts.
+    pub(super) fn next_delay(&self, started: Instant, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_attempts { return None; }
+
+        let delay = self.delay(attempt - 1).max(retry_after.unwrap_or_default());
+        if let Some(deadline) = self.deadline {
+            if started.elapsed() + delay > deadline { return None; }
+        }
+
+        Some(delay)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum WritePolicy {
+    /// Send every write exactly once, except idempotent ones such as an edit by `PUT`.
+    #[default]
+    Never,
+    /// Resend writes only when Tumblr provably did not process them: connect errors and `429`.
+    Unambiguous,
+    /// Like `Unambiguous`, and after an ambiguous failure while creating a post, search the blog for it before resending.
+    Verify(PostMatch),
+}
+
+#[derive(Debug, Clone)]
+pub enum PostMatch {
+    /// Match on the `slug` optional param, which must be set.
+    Slug,
+    /// Match on a hash of the whitespace-normalized body, caption, quote or url.
+    BodyHash,
+    /// Match on a unique tag, appended to the post's tags.
+    Marker(String),
+}
+
+pub(super) trait Status {
+    fn status(&self) -> StatusCode;
+}
+
+impl Status for Response {
+    fn status(&self) -> StatusCode { Response::status(self) }
+}
+
+#[cfg(feature = "async")]
+impl Status for reqwest::r#async::Response {
+    fn status(&self) -> StatusCode { reqwest::r#async::Response::status(self) }
+}
+
+pub(super) enum Attempt<R = Response> {
+    /// Final outcome, never retried.
+    Done(reqwest::Result<R>),
+    /// Tumblr did not process the request, so it is safe to resend.
+    Rejected(reqwest::Result<R>),
+    /// Tumblr may or may not have processed the request.
+    Ambiguous(reqwest::Result<R>),
+}
+
+pub(super) fn classify<R: Status>(result: reqwest::Result<R>) -> Attempt<R> {
+    match result {
+        Ok(resp) => match resp.status() {
+            StatusCode::TOO_MANY_REQUESTS => Attempt::Rejected(Ok(resp)),
+            status if status.is_server_error() => Attempt::Ambiguous(Ok(resp)),
+            _ => Attempt::Done(Ok(resp)),
+        },
+        Err(e) => {
+            let connect = e.get_ref()
+                .and_then(|e| e.downcast_ref::<hyper::Error>())
+                .is_some_and(hyper::Error::is_connect);
+
+            if connect {
+                Attempt::Rejected(Err(e))
+            } else if e.is_timeout() || e.is_http() {
+                Attempt::Ambiguous(Err(e))
+            } else { Attempt::Done(Err(e)) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // --- std ---
+    use std::{
+        io::Read,
+        net::TcpListener,
+        thread,
+    };
+    // --- external ---
+    use reqwest::Client;
+    // --- custom ---
+    use super::*;
+
+    struct Fake(StatusCode);
+
+    impl Status for Fake {
+        fn status(&self) -> StatusCode { self.0 }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new()
+            .max_attempts(10)
+            .base_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5))
+            .jitter(false)
+    }
+
+    fn secs(secs: u64) -> Option<Duration> { Some(Duration::from_secs(secs)) }
+
+    #[test]
+    fn capped_backoff() {
+        let now = Instant::now();
+        let delays = (1..=10).map(|attempt| policy().next_delay(now, attempt, None)).collect::<Vec<_>>();
+        assert_eq!(delays, [secs(1), secs(2), secs(4), secs(5), secs(5), secs(5), secs(5), secs(5), secs(5), None]);
+
+        for attempt in 1..4 {
+            let delay = policy().jitter(true).next_delay(now, attempt, None).unwrap();
+            let max = Duration::from_secs(1 << (attempt - 1));
+            assert!(delay >= max / 2 && delay <= max, "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn retry_after_overrides_shorter_delays() {
+        let now = Instant::now();
+        assert_eq!(policy().next_delay(now, 1, secs(10)), secs(10));
+        assert_eq!(policy().next_delay(now, 3, secs(0)), secs(4));
+        assert_eq!(policy().next_delay(now, 10, secs(1)), None);
+    }
+
+    #[test]
+    fn deadline_cutoff() {
+        let policy = policy().deadline(Duration::from_secs(3));
+        let now = Instant::now();
+        assert_eq!(policy.next_delay(now, 1, None), secs(1));
+        assert_eq!(policy.next_delay(now, 2, None), secs(2));
+        assert_eq!(policy.next_delay(now, 3, None), None);
+        assert_eq!(policy.next_delay(now, 1, secs(5)), None);
+
+        let started = now - Duration::from_millis(2_500);
+        assert_eq!(policy.next_delay(started, 1, None), None);
+        assert_eq!(policy.deadline_at(now), Some(now + Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn writes_are_sent_once() {
+        let now = Instant::now();
+        assert_eq!(policy().for_request(&WritePolicy::Never, false).next_delay(now, 1, None), None);
+        assert_eq!(policy().for_request(&WritePolicy::Never, true).next_delay(now, 1, None), secs(1));
+        assert_eq!(policy().for_request(&WritePolicy::Unambiguous, false).next_delay(now, 1, None), secs(1));
+    }
+
+    #[test]
+    fn classify_statuses() {
+        assert!(matches!(classify(Ok(Fake(StatusCode::OK))), Attempt::Done(Ok(_))));
+        assert!(matches!(classify(Ok(Fake(StatusCode::NOT_FOUND))), Attempt::Done(Ok(_))));
+        assert!(matches!(classify(Ok(Fake(StatusCode::TOO_MANY_REQUESTS))), Attempt::Rejected(Ok(_))));
+        assert!(matches!(classify(Ok(Fake(StatusCode::INTERNAL_SERVER_ERROR))), Attempt::Ambiguous(Ok(_))));
+        assert!(matches!(classify(Ok(Fake(StatusCode::SERVICE_UNAVAILABLE))), Attempt::Ambiguous(Ok(_))));
+    }
+
+    #[test]
+    fn classify_errors() {
+        // nothing listens on a port just released
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        assert!(matches!(classify::<Response>(reqwest::get(&format!("http://{}/", closed))), Attempt::Rejected(Err(_))));
+
+        // a server which reads the request and never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let silent = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]);
+            thread::sleep(Duration::from_secs(5));
+        });
+        let client = Client::builder().timeout(Duration::from_millis(200)).build().unwrap();
+        assert!(matches!(classify(client.get(&format!("http://{}/", silent)).send()), Attempt::Ambiguous(Err(_))));
+
+        assert!(matches!(classify::<Response>(reqwest::get("not a url")), Attempt::Done(Err(_))));
+    }
+}
@@ -1,83 +1,96 @@
 mod api {
     // GET
-    pub const INFO: &'static str = "https://api.tumblr.com/v2/user/info";
-    pub const DASHBOARD: &'static str = "https://api.tumblr.com/v2/user/dashboard";
-    pub const LIKES: &'static str = "https://api.tumblr.com/v2/user/likes";
-    pub const FOLLOWING: &'static str = "https://api.tumblr.com/v2/user/following";
+    pub const INFO: &str = "user/info";
+    pub const DASHBOARD: &str = "user/dashboard";
+    pub const LIKES: &str = "user/likes";
+    pub const FOLLOWING: &str = "user/following";
     // POST
-    pub const FOLLOW: &'static str = "https://api.tumblr.com/v2/user/follow";
-    pub const UNFOLLOW: &'static str = "https://api.tumblr.com/v2/user/unfollow";
-    pub const LIKE: &'static str = "https://api.tumblr.com/v2/user/like";
-    pub const UNLIKE: &'static str = "https://api.tumblr.com/v2/user/unlike";
+    pub const FOLLOW: &str = "user/follow";
+    pub const UNFOLLOW: &str = "user/unfollow";
+    pub const LIKE: &str = "user/like";
+    pub const UNLIKE: &str = "user/unlike";
 }
 
 // --- external ---
+use chrono::{DateTime, Utc};
+use reqwest::Method;
 use serde_json::Value;
 // --- custom ---
-use super::{TumblrClient, build_oauth_headers, build_query, build_params};
+use super::{
+    TumblrClient, TumblrClientError,
+    blog::PostKind,
+    endpoint::Endpoint,
+    model::{Following, Likes, Posts, User, UserInfo},
+    params::{check_exclusive, check_limit, timestamp},
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetUserDashboardOptionalParams {
+    pub(super) limit: Option<u32>,
+    pub(super) offset: Option<u64>,
+    r#type: Option<PostKind>,
+    since_id: Option<u64>,
+    reblog_info: Option<bool>,
+    notes_info: Option<bool>,
+}
 
-#[derive(Default)]
-pub struct GetUserDashboardOptionalParams<'a> {
-    limit: Option<&'a str>,
-    offset: Option<&'a str>,
-    r#type: Option<&'a str>,
-    since_id: Option<&'a str>,
-    reblog_info: Option<&'a str>,
-    notes_info: Option<&'a str>,
+impl GetUserDashboardOptionalParams {
+    set_attr!(self, limit: u32);
+    set_attr!(self, offset: u64);
+    set_attr!(self, r#type: PostKind);
+    set_attr!(self, since_id: u64);
+    set_attr!(self, reblog_info: bool);
+    set_attr!(self, notes_info: bool);
 }
 
-impl<'a> GetUserDashboardOptionalParams<'a> {
-    set_attr!(self, limit);
-    set_attr!(self, offset);
-    set_attr!(self, r#type);
-    set_attr!(self, since_id);
-    set_attr!(self, reblog_info);
-    set_attr!(self, notes_info);
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetUserLikesOptionalParams {
+    pub(super) limit: Option<u32>,
+    pub(super) offset: Option<u64>,
+    pub(super) before: Option<DateTime<Utc>>,
+    pub(super) after: Option<DateTime<Utc>>,
 }
 
-#[derive(Default)]
-pub struct GetUserLikesOptionalParams<'a> {
-    limit: Option<&'a str>,
-    offset: Option<&'a str>,
-    before: Option<&'a str>,
-    after: Option<&'a str>,
+impl GetUserLikesOptionalParams {
+    set_attr!(self, limit: u32);
+    set_attr!(self, offset: u64);
+    set_attr!(self, before: DateTime<Utc>);
+    set_attr!(self, after: DateTime<Utc>);
+
+    fn validate(&self) -> Result<(), TumblrClientError> {
+        check_limit(self.limit)?;
+        check_exclusive(&[("offset", self.offset.is_some()), ("before", self.before.is_some()), ("after", self.after.is_some())])
+    }
 }
 
-impl<'a> GetUserLikesOptionalParams<'a> {
-    set_attr!(self, limit);
-    set_attr!(self, offset);
-    set_attr!(self, before);
-    set_attr!(self, after);
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetUserFollowingOptionalParams {
+    pub(super) limit: Option<u32>,
+    pub(super) offset: Option<u64>,
 }
 
-#[derive(Default)]
-pub struct GetUserFollowingOptionalParams<'a> {
-    limit: Option<&'a str>,
-    offset: Option<&'a str>,
+impl GetUserFollowingOptionalParams {
+    set_attr!(self, limit: u32);
+    set_attr!(self, offset: u64);
 }
 
-impl<'a> GetUserFollowingOptionalParams<'a> {
-    set_attr!(self, limit);
-    set_attr!(self, offset);
+pub struct GetUserInfo;
+
+impl Endpoint for GetUserInfo {
+    type Response = UserInfo;
+
+    fn path(&self) -> String { api::INFO.to_owned() }
 }
 
-impl TumblrClient {
-    pub fn get_user_info(&self) -> Value {
-        let headers = build_oauth_headers(
-            "GET",
-            api::INFO,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            None,
-        );
-
-        self.get(api::INFO, Some(headers))
-            .json()
-            .unwrap()
-    }
+pub struct GetUserDashboard { pub optional_params: Option<GetUserDashboardOptionalParams> }
 
-    pub fn get_user_dashboard(&self, optional_params: Option<GetUserDashboardOptionalParams>) -> Value {
-        let params = if let Some(optional_params) = optional_params {
+impl Endpoint for GetUserDashboard {
+    type Response = Posts;
+
+    fn path(&self) -> String { api::DASHBOARD.to_owned() }
+
+    fn params(&self) -> Vec<(&str, String)> {
+        if let Some(ref optional_params) = self.optional_params {
             set_params![
                 ("limit", optional_params.limit),
                 ("offset", optional_params.offset),
@@ -86,122 +99,131 @@ impl TumblrClient {
                 ("reblog_info", optional_params.reblog_info),
                 ("notes_info", optional_params.notes_info)
             ]
-        } else { vec![] };
-        let url = build_query(api::DASHBOARD, &params);
-        let headers = build_oauth_headers(
-            "GET",
-            api::DASHBOARD,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(params)),
-        );
-
-        self.get(&url, Some(headers))
-            .json()
-            .unwrap()
+        } else { vec![] }
     }
 
-    pub fn get_user_likes(&self, optional_params: Option<GetUserLikesOptionalParams>) -> Value {
-        let params = if let Some(optional_params) = optional_params {
+    fn validate(&self) -> Result<(), TumblrClientError> { check_limit(self.optional_params.as_ref().and_then(|optional_params| optional_params.limit)) }
+}
+
+pub struct GetUserLikes { pub optional_params: Option<GetUserLikesOptionalParams> }
+
+impl Endpoint for GetUserLikes {
+    type Response = Likes;
+
+    fn path(&self) -> String { api::LIKES.to_owned() }
+
+    fn params(&self) -> Vec<(&str, String)> {
+        if let Some(ref optional_params) = self.optional_params {
             set_params![
                 ("limit", optional_params.limit),
                 ("offset", optional_params.offset),
-                ("before", optional_params.before),
-                ("after", optional_params.after)
+                ("before", optional_params.before.as_ref().map(timestamp)),
+                ("after", optional_params.after.as_ref().map(timestamp))
             ]
-        } else { vec![] };
-        let url = build_query(api::LIKES, &params);
-        let headers = build_oauth_headers(
-            "GET",
-            api::LIKES,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(params)),
-        );
-
-        self.get(&url, Some(headers))
-            .json()
-            .unwrap()
+        } else { vec![] }
     }
 
-    pub fn get_user_following(&self, optional_params: Option<GetUserFollowingOptionalParams>) -> Value {
-        let params = if let Some(optional_params) = optional_params {
+    fn validate(&self) -> Result<(), TumblrClientError> { self.optional_params.as_ref().map_or(Ok(()), GetUserLikesOptionalParams::validate) }
+}
+
+pub struct GetUserFollowing { pub optional_params: Option<GetUserFollowingOptionalParams> }
+
+impl Endpoint for GetUserFollowing {
+    type Response = Following;
+
+    fn path(&self) -> String { api::FOLLOWING.to_owned() }
+
+    fn params(&self) -> Vec<(&str, String)> {
+        if let Some(ref optional_params) = self.optional_params {
             set_params![
                 ("limit", optional_params.limit),
                 ("offset", optional_params.offset)
             ]
-        } else { vec![] };
-        let url = build_query(api::FOLLOWING, &params);
-        let headers = build_oauth_headers(
-            "GET",
-            api::FOLLOWING,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(params)),
-        );
-
-        self.get(&url, Some(headers))
-            .json()
-            .unwrap()
+        } else { vec![] }
     }
 
-    pub fn follow_blog(&self, url: &str) -> Value {
-        let form = vec![("url", url)];
-        let headers = build_oauth_headers(
-            "POST",
-            api::FOLLOW,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(form.clone())),
-        );
-
-        self.post(api::FOLLOW, headers, &form)
-            .json()
-            .unwrap()
-    }
+    fn validate(&self) -> Result<(), TumblrClientError> { check_limit(self.optional_params.as_ref().and_then(|optional_params| optional_params.limit)) }
+}
+
+pub struct FollowBlog { pub url: String }
+
+impl Endpoint for FollowBlog {
+    type Response = Value;
+
+    fn method(&self) -> Method { Method::POST }
+
+    fn path(&self) -> String { api::FOLLOW.to_owned() }
+
+    fn params(&self) -> Vec<(&str, String)> { vec![("url", self.url.to_owned())] }
+}
 
-    pub fn unfollow_blog(&self, url: &str) -> Value {
-        let form = vec![("url", url)];
-        let headers = build_oauth_headers(
-            "POST",
-            api::UNFOLLOW,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(form.clone())),
-        );
-
-        self.post(api::UNFOLLOW, headers, &form)
-            .json()
-            .unwrap()
+pub struct UnfollowBlog { pub url: String }
+
+impl Endpoint for UnfollowBlog {
+    type Response = Value;
+
+    fn method(&self) -> Method { Method::POST }
+
+    fn path(&self) -> String { api::UNFOLLOW.to_owned() }
+
+    fn params(&self) -> Vec<(&str, String)> { vec![("url", self.url.to_owned())] }
+}
+
+pub struct LikePost {
+    pub id: String,
+    pub reblog_key: String,
+}
+
+impl Endpoint for LikePost {
+    type Response = Value;
+
+    fn method(&self) -> Method { Method::POST }
+
+    fn path(&self) -> String { api::LIKE.to_owned() }
+
+    fn params(&self) -> Vec<(&str, String)> { vec![("id", self.id.to_owned()), ("reblog_key", self.reblog_key.to_owned())] }
+}
+
+pub struct UnlikePost {
+    pub id: String,
+    pub reblog_key: String,
+}
+
+impl Endpoint for UnlikePost {
+    type Response = Value;
+
+    fn method(&self) -> Method { Method::POST }
+
+    fn path(&self) -> String { api::UNLIKE.to_owned() }
+
+    fn params(&self) -> Vec<(&str, String)> { vec![("id", self.id.to_owned()), ("reblog_key", self.reblog_key.to_owned())] }
+}
+
+impl TumblrClient {
+    /// Check the credentials, returning the authenticated user and their blogs.
+    ///
+    /// Fails with an error for which `is_unauthorized` holds if they are invalid, expired or revoked.
+    pub fn verify_credentials(&self) -> Result<User, TumblrClientError> { Ok(self.get_user_info()?.user) }
+
+    pub fn get_user_info(&self) -> Result<UserInfo, TumblrClientError> { self.call(GetUserInfo) }
+
+    pub fn get_user_dashboard(&self, optional_params: Option<GetUserDashboardOptionalParams>) -> Result<Posts, TumblrClientError> {
+        self.call(GetUserDashboard { optional_params })
     }
 
-    pub fn like_post(&self, id: &str, reblog_key: &str) -> Value {
-        let form = vec![("id", id), ("reblog_key", reblog_key)];
-        let headers = build_oauth_headers(
-            "POST",
-            api::LIKE,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(form.clone())),
-        );
-
-        self.post(api::LIKE, headers, &form)
-            .json()
-            .unwrap()
+    pub fn get_user_likes(&self, optional_params: Option<GetUserLikesOptionalParams>) -> Result<Likes, TumblrClientError> {
+        self.call(GetUserLikes { optional_params })
     }
 
-    pub fn unlike_post(&self, id: &str, reblog_key: &str) -> Value {
-        let form = vec![("id", id), ("reblog_key", reblog_key)];
-        let headers = build_oauth_headers(
-            "POST",
-            api::UNLIKE,
-            &self.keys.consumer(),
-            Some(&self.keys.token()),
-            Some(&build_params(form.clone())),
-        );
-
-        self.post(api::UNLIKE, headers, &form)
-            .json()
-            .unwrap()
+    pub fn get_user_following(&self, optional_params: Option<GetUserFollowingOptionalParams>) -> Result<Following, TumblrClientError> {
+        self.call(GetUserFollowing { optional_params })
     }
+
+    pub fn follow_blog(&self, url: &str) -> Result<(), TumblrClientError> { self.call(FollowBlog { url: url.to_owned() }).map(|_| ()) }
+
+    pub fn unfollow_blog(&self, url: &str) -> Result<(), TumblrClientError> { self.call(UnfollowBlog { url: url.to_owned() }).map(|_| ()) }
+
+    pub fn like_post(&self, id: &str, reblog_key: &str) -> Result<(), TumblrClientError> { self.call(LikePost { id: id.to_owned(), reblog_key: reblog_key.to_owned() }).map(|_| ()) }
+
+    pub fn unlike_post(&self, id: &str, reblog_key: &str) -> Result<(), TumblrClientError> { self.call(UnlikePost { id: id.to_owned(), reblog_key: reblog_key.to_owned() }).map(|_| ()) }
 }
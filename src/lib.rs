@@ -1,28 +1,144 @@
+#![allow(non_local_definitions)]
+
+extern crate argon2;
+extern crate base64;
+extern crate chacha20poly1305;
+extern crate chrono;
+extern crate dirs;
 #[macro_use]
 extern crate failure;
+#[cfg(feature = "async")]
+extern crate futures;
+extern crate hyper;
 extern crate oauth_client;
+extern crate percent_encoding;
+extern crate rand;
 extern crate reqwest;
+extern crate ring;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
+extern crate serde_urlencoded;
+#[cfg(feature = "testing")]
+extern crate tiny_http;
+extern crate toml;
+#[cfg(feature = "async")]
+extern crate tokio_timer;
+
+pub use chrono::{DateTime, Utc};
+pub use reqwest::Method;
 
 pub use self::client::{
+    ApiError,
     TumblrClient,
+    TumblrClientError,
     blog::{
+        AskLayout,
+        Attribution,
+        AudioBlock,
+        BlogRef,
+        CondensedLayout,
+        ContentBlock,
+        DeletePost,
+        EmbedIframe,
+        Filter,
+        Formatting,
+        GetBlogAvatar,
         GetBlogAvatarOptionalParams,
-        GetBlogLikesOptionalParams,
-        GetBlogFollowingOptionalParams,
+        GetBlogFollowers,
         GetBlogFollowersOptionalParams,
+        GetBlogFollowing,
+        GetBlogFollowingOptionalParams,
+        GetBlogInfo,
+        GetBlogLikes,
+        GetBlogLikesOptionalParams,
+        GetBlogPosts,
         GetBlogPostsOptionalParams,
-        GetBlogPostsQueueOptionalParams,
+        GetBlogPostsDraft,
         GetBlogPostsDraftOptionalParams,
+        GetBlogPostsQueue,
+        GetBlogPostsQueueOptionalParams,
+        GetBlogPostsSubmission,
         GetBlogPostsSubmissionOptionalParams,
+        ImageBlock,
+        Interactability,
+        Layout,
+        LegacyPost,
         LegacyPostOptionalParams,
+        LinkBlock,
+        Media,
+        MediaObject,
+        NpfAction,
+        NpfContent,
+        NpfPost,
+        NpfPostOptionalParams,
+        NpfReblog,
+        PaywallBlock,
+        PollAnswer,
+        PollBlock,
+        PollSettings,
         PostAction,
-        PostType
+        PostKind,
+        PostRef,
+        PostState,
+        PostType,
+        Row,
+        RowsLayout,
+        Style,
+        TextBlock,
+        TextFormat,
+        TextSubtype,
+        VideoBlock,
+    },
+    model::{
+        Blog,
+        BlogInfo,
+        Dialogue,
+        Envelope,
+        Follower,
+        Followers,
+        Following,
+        Likes,
+        Meta,
+        Note,
+        Photo,
+        PhotoSize,
+        Post,
+        PostContent,
+        PostId,
+        Posts,
+        User,
+        UserInfo,
+        VideoPlayer,
+    },
+    builder::TumblrClientBuilder,
+    cassette::{Cassette, CassetteRequest, CassetteResponse, Interaction},
+    endpoint::Endpoint,
+    oauth::RequestToken,
+    oauth2::{OAuth2Token, Scope},
+    paginate::PageIter,
+    raw::{Auth, FormField, RequestBody},
+    rate_limit::{Quota, RateLimit, Throttle},
+    retry::{PostMatch, RetryPolicy, WritePolicy},
+    user::{
+        FollowBlog,
+        GetUserDashboard,
+        GetUserDashboardOptionalParams,
+        GetUserFollowing,
+        GetUserFollowingOptionalParams,
+        GetUserInfo,
+        GetUserLikes,
+        GetUserLikesOptionalParams,
+        LikePost,
+        UnfollowBlog,
+        UnlikePost,
     },
-    user::{GetUserDashboardOptionalParams, GetUserLikesOptionalParams, GetUserFollowingOptionalParams},
 };
 
+#[cfg(feature = "async")]
+pub use self::client::asynchronous::{AsyncTumblrClient, TumblrFuture, TumblrStream};
+
 mod client;
+#[cfg(feature = "testing")]
+pub mod testing;
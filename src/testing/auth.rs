@@ -0,0 +1,123 @@
+// --- std ---
+use std::collections::{BTreeMap, HashMap};
+// --- external ---
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode};
+use rand::{Rng, distributions::Alphanumeric};
+use ring::hmac;
+
+/// Keys of the app and the user, and every token issued so far.
+#[derive(Debug)]
+pub(super) struct Auth {
+    pub consumer_key: String,
+    pub consumer_secret: String,
+    pub access_key: String,
+    pub access_secret: String,
+    pub revoked: bool,
+    /// Request token key to its secret, callback and verifier once authorized.
+    pub request_tokens: HashMap<String, (String, Option<String>, Option<String>)>,
+    /// Authorization code to its scope.
+    pub codes: HashMap<String, String>,
+    /// Valid OAuth2 access tokens.
+    pub bearer_tokens: Vec<String>,
+    /// Refresh token to its scope.
+    pub refresh_tokens: HashMap<String, String>,
+}
+
+/// Who signed a request.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum Signer {
+    Nobody,
+    /// Signed with the consumer keys only, or with a request token.
+    Consumer,
+    User,
+    Invalid,
+}
+
+impl Auth {
+    pub fn new(consumer_key: &str, consumer_secret: &str, access_key: &str, access_secret: &str) -> Auth {
+        Auth {
+            consumer_key: consumer_key.to_owned(),
+            consumer_secret: consumer_secret.to_owned(),
+            access_key: access_key.to_owned(),
+            access_secret: access_secret.to_owned(),
+            revoked: false,
+            request_tokens: HashMap::new(),
+            codes: HashMap::new(),
+            bearer_tokens: vec![],
+            refresh_tokens: HashMap::new(),
+        }
+    }
+
+    /// Check the `Authorization` header of a request to `url` (without its query) carrying `params`.
+    pub fn signer(&self, method: &str, url: &str, params: &[(String, String)], authorization: Option<&str>) -> Signer {
+        let authorization = match authorization {
+            Some(authorization) => authorization,
+            None => return Signer::Nobody,
+        };
+        if let Some(token) = authorization.strip_prefix("Bearer ") {
+            return if self.bearer_tokens.iter().any(|bearer| bearer == token) { Signer::User } else { Signer::Invalid };
+        }
+
+        let oauth = match parse_header(authorization) {
+            Some(oauth) => oauth,
+            None => return Signer::Invalid,
+        };
+        if oauth.get("oauth_consumer_key") != Some(&self.consumer_key) { return Signer::Invalid; }
+
+        let (token_secret, signer) = match oauth.get("oauth_token") {
+            None => (None, Signer::Consumer),
+            Some(token) if *token == self.access_key && !self.revoked => (Some(self.access_secret.as_str()), Signer::User),
+            Some(token) => match self.request_tokens.get(token) {
+                Some((secret, ..)) => (Some(secret.as_str()), Signer::Consumer),
+                None => return Signer::Invalid,
+            },
+        };
+
+        if verify(method, url, params, &oauth, &self.consumer_secret, token_secret) { signer } else { Signer::Invalid }
+    }
+}
+
+/// The decoded `oauth_*` parameters of an `OAuth ...` authorization header.
+pub(super) fn parse_header(header: &str) -> Option<BTreeMap<String, String>> {
+    header.strip_prefix("OAuth ")?
+        .split(',')
+        .map(|param| {
+            let (k, v) = param.trim().split_once('=')?;
+            let v = percent_decode_str(v.trim_matches('"')).decode_utf8().ok()?;
+
+            Some((k.to_owned(), v.into_owned()))
+        })
+        .collect()
+}
+
+/// Recompute the signature with the request's own nonce and timestamp, and compare.
+fn verify(method: &str, url: &str, params: &[(String, String)], oauth: &BTreeMap<String, String>, consumer_secret: &str, token_secret: Option<&str>) -> bool {
+    let signature = match oauth.get("oauth_signature") {
+        Some(signature) => signature,
+        None => return false,
+    };
+
+    // every param, repeated names included, sorted by name then value
+    let mut params = params.iter()
+        .map(|(k, v)| (k, v))
+        .chain(oauth.iter().filter(|&(k, _)| k != "oauth_signature"))
+        .map(|(k, v)| (encode(k), encode(v)))
+        .collect::<Vec<_>>();
+    params.sort_unstable();
+    let params = params.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("&");
+    let base = format!("{}&{}&{}", encode(method), encode(url), encode(&params));
+
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, format!("{}&{}", encode(consumer_secret), encode(token_secret.unwrap_or(""))).as_bytes());
+    base64::encode(hmac::sign(&key, base.as_bytes()).as_ref()) == *signature
+}
+
+const RFC3986: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+fn encode(s: &str) -> String { utf8_percent_encode(s, RFC3986).to_string() }
+
+pub(super) fn random_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(24)
+        .collect()
+}
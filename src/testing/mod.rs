@@ -0,0 +1,163 @@
+//! An in-process fake Tumblr to test against without network, enabled by the `testing` feature.
+//!
+//! It serves the v2 endpoints rumblr calls and the OAuth ones from seedable in-memory state,
+//! verifies OAuth signatures, and can be told to fail requests.
+
+mod auth;
+mod routes;
+mod state;
+
+pub use self::state::{FakeBlog, State};
+
+// --- std ---
+use std::{
+    io,
+    sync::{Arc, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+// --- external ---
+use tiny_http::Server;
+// --- custom ---
+use self::auth::Auth;
+use crate::TumblrClient;
+
+pub const CONSUMER_KEY: &str = "fake-consumer-key";
+pub const CONSUMER_SECRET: &str = "fake-consumer-secret";
+pub const ACCESS_KEY: &str = "fake-access-key";
+pub const ACCESS_SECRET: &str = "fake-access-secret";
+
+#[derive(Debug, Clone)]
+pub enum Failure {
+    /// Answer with this status without processing the request, e.g. `500`.
+    Status(u16),
+    /// Answer `429` without processing the request, with a `Retry-After` of that many seconds if any.
+    TooManyRequests(Option<u64>),
+    /// Process the request, then answer with this status anyway, as when Tumblr fails after a write.
+    AfterProcessing(u16),
+    /// Hold the response back this long, to trip the client's timeout.
+    Delay(Duration),
+}
+
+/// A request the fake received.
+#[derive(Debug, Clone)]
+pub struct Recorded {
+    pub method: String,
+    /// Without the query.
+    pub path: String,
+    /// The query and form parameters, decoded.
+    pub params: Vec<(String, String)>,
+    pub authorization: Option<String>,
+}
+
+impl Recorded {
+    pub fn param(&self, name: &str) -> Option<&str> { self.params.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str()) }
+}
+
+#[derive(Debug)]
+struct Fault {
+    path: String,
+    failure: Failure,
+    times: usize,
+}
+
+#[derive(Debug)]
+struct Shared {
+    url: String,
+    state: Mutex<State>,
+    auth: Mutex<Auth>,
+    faults: Mutex<Vec<Fault>>,
+    requests: Mutex<Vec<Recorded>>,
+}
+
+/// A fake Tumblr listening on 127.0.0.1, stopped when dropped.
+pub struct FakeTumblr {
+    server: Arc<Server>,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FakeTumblr {
+    /// Start with `State::new("rumblr")`.
+    pub fn start() -> Result<FakeTumblr, io::Error> { FakeTumblr::with_state(State::new("rumblr")) }
+
+    pub fn with_state(state: State) -> Result<FakeTumblr, io::Error> {
+        let server = Arc::new(Server::http("127.0.0.1:0").map_err(|e| io::Error::other(e.to_string()))?);
+        let shared = Arc::new(Shared {
+            url: format!("http://{}/", server.server_addr()),
+            state: Mutex::new(state),
+            auth: Mutex::new(Auth::new(CONSUMER_KEY, CONSUMER_SECRET, ACCESS_KEY, ACCESS_SECRET)),
+            faults: Mutex::new(vec![]),
+            requests: Mutex::new(vec![]),
+        });
+
+        let thread = {
+            let server = server.clone();
+            let shared = shared.clone();
+
+            thread::spawn(move || for request in server.incoming_requests() {
+                let shared = shared.clone();
+                thread::spawn(move || routes::handle(&shared, request));
+            })
+        };
+
+        Ok(FakeTumblr {
+            server,
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// The OAuth base url, e.g. `http://127.0.0.1:4321/`.
+    pub fn url(&self) -> &str { &self.shared.url }
+
+    /// The API base url, e.g. `http://127.0.0.1:4321/v2/`.
+    pub fn api_url(&self) -> String { format!("{}v2/", self.shared.url) }
+
+    /// A client authorized as the user.
+    pub fn client(&self) -> TumblrClient {
+        self.configure(TumblrClient::with_keys(CONSUMER_KEY, CONSUMER_SECRET, ACCESS_KEY, ACCESS_SECRET))
+    }
+
+    /// A client with the consumer keys only, to go through the OAuth flows.
+    pub fn consumer_client(&self) -> TumblrClient { self.configure(TumblrClient::new().set_consumer(CONSUMER_KEY, CONSUMER_SECRET)) }
+
+    /// Point `client` at this fake.
+    pub fn configure(&self, client: TumblrClient) -> TumblrClient {
+        client
+            .api_base_url(&self.api_url())
+            .and_then(|client| client.oauth_base_url(self.url()))
+            .expect("the fake's urls are valid")
+    }
+
+    /// Lock the state, to seed or inspect it.
+    pub fn state(&self) -> MutexGuard<'_, State> { lock(&self.shared.state) }
+
+    /// Fail the next `times` requests whose path contains `path`; `""` matches every request.
+    pub fn fail(&self, path: &str, failure: Failure, times: usize) {
+        lock(&self.shared.faults).push(Fault {
+            path: path.to_owned(),
+            failure,
+            times,
+        });
+    }
+
+    /// Every request received so far.
+    pub fn requests(&self) -> Vec<Recorded> { lock(&self.shared.requests).clone() }
+
+    /// Reject the user's access key from now on, with `401`.
+    pub fn revoke_access(&self) { lock(&self.shared.auth).revoked = true; }
+
+    /// Reject every OAuth2 access token issued so far, as if they expired; refresh tokens stay valid.
+    pub fn expire_oauth2_tokens(&self) { lock(&self.shared.auth).bearer_tokens.clear(); }
+}
+
+impl Drop for FakeTumblr {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() { let _ = thread.join(); }
+    }
+}
+
+/// A test failing while holding a lock must not take every following request down with it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> { mutex.lock().unwrap_or_else(|e| e.into_inner()) }
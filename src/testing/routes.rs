@@ -0,0 +1,770 @@
+// --- std ---
+use std::thread;
+// --- external ---
+use reqwest::Url;
+use serde_json::{Map, Value, json};
+use tiny_http::{Header, Request, Response};
+// --- custom ---
+use super::{
+    Failure, Recorded, Shared, State, lock,
+    auth::{Signer, parse_header, random_token},
+    state::now,
+};
+use crate::{Blog, Post};
+
+const LIMIT: usize = 20;
+
+struct Reply {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn json(status: u16, json: &Value) -> Reply {
+        Reply {
+            status,
+            headers: vec![("Content-Type", "application/json".to_owned())],
+            body: json.to_string().into_bytes(),
+        }
+    }
+
+    fn ok(response: Value) -> Reply { Reply::envelope(200, "OK", response) }
+
+    fn envelope(status: u16, msg: &str, response: Value) -> Reply {
+        Reply::json(status, &json!({
+            "meta": { "status": status, "msg": msg },
+            "response": response,
+        }))
+    }
+
+    fn error(status: u16, detail: &str) -> Reply {
+        let msg = tiny_http::StatusCode(status).default_reason_phrase();
+
+        Reply::json(status, &json!({
+            "meta": { "status": status, "msg": msg },
+            "response": [],
+            "errors": [{ "title": msg, "code": 0, "detail": detail }],
+        }))
+    }
+
+    fn text(status: u16, content_type: &str, body: String) -> Reply {
+        Reply {
+            status,
+            headers: vec![("Content-Type", content_type.to_owned())],
+            body: body.into_bytes(),
+        }
+    }
+
+    fn redirect(location: Url) -> Reply {
+        Reply {
+            status: 302,
+            headers: vec![("Location", location.into_string())],
+            body: vec![],
+        }
+    }
+}
+
+struct Incoming {
+    method: String,
+    path: String,
+    /// Query and form parameters, which OAuth signatures cover, with the text fields of a multipart body.
+    params: Vec<(String, String)>,
+    /// A JSON body.
+    json: Option<Value>,
+    /// The parts of a multipart body which aren't text fields.
+    uploads: Vec<Upload>,
+    authorization: Option<String>,
+}
+
+/// A part of a multipart body.
+struct Upload {
+    name: String,
+    content_type: Option<String>,
+    file_name: Option<String>,
+    data: Vec<u8>,
+}
+
+impl Incoming {
+    fn param(&self, name: &str) -> Option<&str> { self.params.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str()) }
+
+    fn number(&self, name: &str) -> Option<u64> { self.param(name).and_then(|v| v.parse().ok()) }
+
+    /// `offset` and `limit` applied to `items`.
+    fn page<T: Clone>(&self, items: &[T]) -> Vec<T> {
+        let limit = self.number("limit").map_or(LIMIT, |limit| (limit as usize).clamp(1, LIMIT));
+
+        items.iter()
+            .skip(self.number("offset").unwrap_or(0) as usize)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// `page` of `posts`, in the legacy format unless `npf=true`.
+    fn posts(&self, posts: &[Post]) -> Vec<Value> {
+        let npf = self.param("npf") == Some("true");
+
+        self.page(posts)
+            .into_iter()
+            .map(|post| {
+                let mut post = json!(post);
+                if !npf {
+                    let fields = post.as_object_mut().expect("posts are objects");
+                    fields.remove("content");
+                    fields.remove("layout");
+                }
+
+                post
+            })
+            .collect()
+    }
+}
+
+pub(super) fn handle(shared: &Shared, mut request: Request) {
+    let incoming = parse(&mut request);
+    lock(&shared.requests).push(Recorded {
+        method: incoming.method.clone(),
+        path: incoming.path.clone(),
+        params: incoming.params.clone(),
+        authorization: incoming.authorization.clone(),
+    });
+
+    let reply = match fault(shared, &incoming.path) {
+        Some(Failure::Status(status)) => Reply::error(status, "injected failure"),
+        Some(Failure::TooManyRequests(retry_after)) => {
+            let mut reply = Reply::error(429, "injected failure");
+            if let Some(retry_after) = retry_after { reply.headers.push(("Retry-After", retry_after.to_string())); }
+
+            reply
+        }
+        Some(Failure::AfterProcessing(status)) => {
+            route(shared, &incoming);
+            Reply::error(status, "injected failure")
+        }
+        Some(Failure::Delay(delay)) => {
+            thread::sleep(delay);
+            route(shared, &incoming)
+        }
+        None => route(shared, &incoming),
+    };
+
+    let mut response = Response::from_data(reply.body).with_status_code(reply.status);
+    for (name, value) in reply.headers {
+        if let Ok(header) = Header::from_bytes(name, value) { response.add_header(header); }
+    }
+    // The client may have given up already.
+    let _ = request.respond(response);
+}
+
+fn parse(request: &mut Request) -> Incoming {
+    let header = |name: &'static str| request.headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str().to_owned());
+    let authorization = header("Authorization");
+    let content_type = header("Content-Type").unwrap_or_default();
+
+    let (path, query) = match request.url().split_once('?') {
+        Some((path, query)) => (path.to_owned(), query.to_owned()),
+        None => (request.url().to_owned(), String::new()),
+    };
+    let mut params = decode(&query);
+    let mut json = None;
+    let mut uploads = vec![];
+    let mut body = vec![];
+    let _ = request.as_reader().read_to_end(&mut body);
+    if content_type.starts_with("application/x-www-form-urlencoded") {
+        params.extend(decode(&String::from_utf8_lossy(&body)));
+    } else if content_type.starts_with("application/json") {
+        json = serde_json::from_slice(&body).ok();
+    } else if let Some((_, boundary)) = content_type.split_once("boundary=") {
+        for upload in multipart(&body, boundary) {
+            match upload {
+                Upload { name, content_type: None, file_name: None, data } => params.push((name, String::from_utf8_lossy(&data).into_owned())),
+                upload => uploads.push(upload),
+            }
+        }
+    }
+
+    Incoming {
+        method: request.method().as_str().to_owned(),
+        path,
+        params,
+        json,
+        uploads,
+        authorization,
+    }
+}
+
+fn decode(params: &str) -> Vec<(String, String)> { serde_urlencoded::from_str(params).unwrap_or_default() }
+
+/// The parts of a `multipart/form-data` body, in order.
+fn multipart(body: &[u8], boundary: &str) -> Vec<Upload> {
+    let delimiter = format!("--{}", boundary.trim_matches('"'));
+    let find = |haystack: &[u8], needle: &[u8]| haystack.windows(needle.len()).position(|window| window == needle);
+
+    let mut uploads = vec![];
+    let mut rest = match find(body, delimiter.as_bytes()) {
+        Some(start) => &body[start + delimiter.len()..],
+        None => return uploads,
+    };
+    // every part but the closing `--` starts with a line break
+    while let Some(part) = rest.strip_prefix(b"\r\n") {
+        let end = match find(part, format!("\r\n{}", delimiter).as_bytes()) {
+            Some(end) => end,
+            None => break,
+        };
+        rest = &part[end + 2 + delimiter.len()..];
+
+        let part = &part[..end];
+        let split = match find(part, b"\r\n\r\n") {
+            Some(split) => split,
+            None => continue,
+        };
+        let headers = String::from_utf8_lossy(&part[..split]);
+        let header = |name: &str| headers.lines()
+            .find_map(|line| line.split_once(':').filter(|(k, _)| k.trim().eq_ignore_ascii_case(name)))
+            .map(|(_, v)| v.trim().to_owned());
+        let disposition = header("Content-Disposition").unwrap_or_default();
+        let disposition_param = |key: &str| disposition.split(';')
+            .find_map(|param| param.trim().strip_prefix(key))
+            .map(|value| value.trim_matches('"').to_owned());
+
+        uploads.push(Upload {
+            name: disposition_param("name=").unwrap_or_default(),
+            content_type: header("Content-Type"),
+            file_name: disposition_param("filename="),
+            data: part[split + 4..].to_vec(),
+        });
+    }
+
+    uploads
+}
+
+/// The first matching injected failure, used up once.
+fn fault(shared: &Shared, path: &str) -> Option<Failure> {
+    let mut faults = lock(&shared.faults);
+    let i = faults.iter().position(|fault| path.contains(&fault.path))?;
+    let failure = faults[i].failure.clone();
+    faults[i].times -= 1;
+    if faults[i].times == 0 { faults.remove(i); }
+
+    Some(failure)
+}
+
+fn route(shared: &Shared, incoming: &Incoming) -> Reply {
+    let segments = incoming.path.trim_start_matches('/').split('/').collect::<Vec<_>>();
+
+    match (incoming.method.as_str(), segments.as_slice()) {
+        ("GET", ["oauth", "request_token"]) | ("POST", ["oauth", "request_token"]) => request_token(shared, incoming),
+        ("GET", ["oauth", "authorize"]) => authorize(shared, incoming),
+        ("GET", ["oauth", "access_token"]) | ("POST", ["oauth", "access_token"]) => access_token(shared, incoming),
+        ("GET", ["oauth2", "authorize"]) => oauth2_authorize(shared, incoming),
+        ("POST", ["v2", "oauth2", "token"]) => oauth2_token(shared, incoming),
+        (_, ["v2", "blog", blog, endpoint @ ..]) => {
+            let user = match endpoint {
+                ["info"] | ["likes"] | ["avatar", ..] => false,
+                ["posts"] => incoming.method != "GET",
+                ["posts", r#type] => !matches!(*r#type, "text" | "quote" | "link" | "answer" | "video" | "audio" | "photo" | "chat"),
+                _ => true,
+            };
+            if let Err(reply) = if user { require_user(shared, incoming) } else { require_api_key(shared, incoming) } { return reply; }
+
+            // Only the user sees their private posts.
+            let owner = user || matches!(signer(shared, incoming), Signer::User);
+            let mut state = lock(&shared.state);
+            if state.blog(blog).is_none() { return Reply::error(404, "blog not found"); }
+
+            blog_route(&mut state, blog, &incoming.method, endpoint, incoming, owner)
+        }
+        (_, ["v2", "user", endpoint]) => {
+            if let Err(reply) = require_user(shared, incoming) { return reply; }
+
+            user_route(&mut lock(&shared.state), &incoming.method, endpoint, incoming)
+        }
+        _ => Reply::error(404, "no such endpoint"),
+    }
+}
+
+fn blog_route(state: &mut State, blog: &str, method: &str, endpoint: &[&str], incoming: &Incoming, owner: bool) -> Reply {
+    let fake_blog = match state.blog(blog) {
+        Some(fake_blog) => fake_blog,
+        None => return Reply::error(404, "blog not found"),
+    };
+
+    match (method, endpoint) {
+        ("GET", ["info"]) => Reply::ok(json!({ "blog": fake_blog.info() })),
+        ("GET", ["posts", "queue"]) => Reply::ok(json!({ "posts": incoming.posts(&fake_blog.queue) })),
+        ("GET", ["posts", "draft"]) => {
+            let drafts = fake_blog.drafts.iter()
+                .filter(|post| incoming.number("before_id").is_none_or(|before_id| post.id < before_id))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            Reply::ok(json!({ "posts": incoming.posts(&drafts) }))
+        }
+        ("GET", ["posts", "submission"]) => Reply::ok(json!({ "posts": incoming.posts(&fake_blog.submissions) })),
+        ("GET", ["posts"]) | ("GET", ["posts", _]) => {
+            let r#type = endpoint.get(1).copied().or_else(|| incoming.param("type"));
+            let posts = fake_blog.posts.iter()
+                .filter(|post| owner || post.state != "private")
+                .filter(|post| r#type.is_none_or(|r#type| post_type(post) == r#type))
+                .filter(|post| incoming.number("id").is_none_or(|id| post.id == id))
+                .filter(|post| incoming.param("tag").is_none_or(|tag| post.tags.iter().any(|t| t == tag)))
+                .filter(|post| incoming.number("before").is_none_or(|before| post.timestamp < before))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            Reply::ok(json!({
+                "blog": fake_blog.info(),
+                "posts": incoming.posts(&posts),
+                "total_posts": posts.len(),
+            }))
+        }
+        ("GET", ["likes"]) => Reply::ok(likes(&fake_blog.likes, incoming)),
+        ("GET", ["followers"]) => Reply::ok(json!({
+            "total_users": fake_blog.followers.len(),
+            "users": incoming.page(&fake_blog.followers),
+        })),
+        ("GET", ["following"]) => Reply::ok(json!({
+            "total_blogs": fake_blog.following.len(),
+            "blogs": incoming.page(&fake_blog.following),
+        })),
+        ("POST", ["posts"]) => match npf_post(state, blog, incoming) {
+            Ok(id) => Reply::envelope(201, "Created", json!({ "id": id.to_string() })),
+            Err(reply) => reply,
+        },
+        ("POST", ["post"]) => match new_post(state, blog, incoming) {
+            Ok(id) => Reply::envelope(201, "Created", json!({ "id": id, "id_string": id.to_string() })),
+            Err(reply) => reply,
+        },
+        ("POST", ["post", "edit"]) => match edit_post(state, blog, incoming) {
+            Ok(id) => Reply::ok(json!({ "id": id, "id_string": id.to_string() })),
+            Err(reply) => reply,
+        },
+        ("POST", ["post", "reblog"]) => match reblog_post(state, blog, incoming) {
+            Ok(id) => Reply::envelope(201, "Created", json!({ "id": id, "id_string": id.to_string() })),
+            Err(reply) => reply,
+        },
+        ("POST", ["post", "delete"]) => {
+            let id = match incoming.number("id") {
+                Some(id) => id,
+                None => return Reply::error(400, "id is required"),
+            };
+            let mut deleted = false;
+            if let Some(fake_blog) = state.blog_mut(blog) {
+                for posts in fake_blog.all_mut() {
+                    let before = posts.len();
+                    posts.retain(|post| post.id != id);
+                    deleted |= posts.len() != before;
+                }
+            }
+
+            if deleted { Reply::ok(json!({ "id": id, "id_string": id.to_string() })) } else { Reply::error(404, "post not found") }
+        }
+        _ => Reply::error(404, "no such endpoint"),
+    }
+}
+
+fn user_route(state: &mut State, method: &str, endpoint: &str, incoming: &Incoming) -> Reply {
+    match (method, endpoint) {
+        ("GET", "info") => Reply::ok(json!({ "user": state.user() })),
+        ("GET", "dashboard") => {
+            let posts = state.dashboard.iter()
+                .filter(|post| incoming.param("type").is_none_or(|r#type| post_type(post) == r#type))
+                .filter(|post| incoming.number("since_id").is_none_or(|since_id| post.id > since_id))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            Reply::ok(json!({ "posts": incoming.page(&posts) }))
+        }
+        ("GET", "likes") => Reply::ok(likes(&state.likes, incoming)),
+        ("GET", "following") => Reply::ok(json!({
+            "total_blogs": state.following.len(),
+            "blogs": incoming.page(&state.following),
+        })),
+        ("POST", "follow") | ("POST", "unfollow") => {
+            let name = match incoming.param("url") {
+                Some(url) => url.trim_start_matches("https://")
+                    .trim_start_matches("http://")
+                    .trim_end_matches('/')
+                    .trim_end_matches(".tumblr.com")
+                    .to_owned(),
+                None => return Reply::error(400, "url is required"),
+            };
+            state.following.retain(|blog| blog.name != name);
+            if endpoint == "follow" {
+                let blog = state.blog(&name).map(|blog| blog.info()).unwrap_or_else(|| Blog {
+                    url: format!("https://{}.tumblr.com/", name),
+                    name,
+                    ..Blog::default()
+                });
+                state.following.insert(0, blog);
+            }
+
+            Reply::ok(json!([]))
+        }
+        ("POST", "like") | ("POST", "unlike") => {
+            let (id, reblog_key) = match (incoming.number("id"), incoming.param("reblog_key")) {
+                (Some(id), Some(reblog_key)) => (id, reblog_key),
+                _ => return Reply::error(400, "id and reblog_key are required"),
+            };
+            let mut post = match state.post(id) {
+                Some((_, post)) if post.reblog_key == reblog_key => post.clone(),
+                Some(_) => return Reply::error(400, "wrong reblog_key"),
+                None => return Reply::error(404, "post not found"),
+            };
+            state.likes.retain(|liked| liked.id != id);
+            if endpoint == "like" {
+                let latest = state.likes.first().and_then(|liked| liked.liked_timestamp).unwrap_or(0);
+                post.liked = Some(true);
+                post.liked_timestamp = Some(now().max(latest + 1));
+                state.likes.insert(0, post);
+            }
+
+            Reply::ok(json!([]))
+        }
+        _ => Reply::error(404, "no such endpoint"),
+    }
+}
+
+fn likes(liked: &[Post], incoming: &Incoming) -> Value {
+    let timestamp = |post: &Post| post.liked_timestamp.unwrap_or(post.timestamp);
+    let mut posts = liked.iter()
+        .filter(|post| incoming.number("before").is_none_or(|before| timestamp(post) < before))
+        .filter(|post| incoming.number("after").is_none_or(|after| timestamp(post) > after))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    // like Tumblr, `after` alone answers with the likes right after it, still newest first
+    let forward = incoming.param("after").is_some() && incoming.param("before").is_none();
+    if forward { posts.reverse(); }
+    let mut page = incoming.page(&posts);
+    if forward { page.reverse(); }
+
+    json!({
+        "liked_posts": page,
+        "liked_count": liked.len(),
+    })
+}
+
+fn post_type(post: &Post) -> String {
+    serde_json::to_value(&post.content)
+        .ok()
+        .and_then(|content| content["type"].as_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
+/// The legacy params of a post, as the fields of a `Post`.
+fn post_fields(incoming: &Incoming, fields: &mut Map<String, Value>) {
+    for (k, v) in &incoming.params {
+        let (field, value) = match k.as_str() {
+            "tags" => ("tags", Value::from(v.split(',').map(str::trim).filter(|tag| !tag.is_empty()).collect::<Vec<_>>())),
+            "quote" => ("text", Value::from(v.as_str())),
+            "conversation" => ("body", Value::from(v.as_str())),
+            "external_url" | "embed" => ("source_url", Value::from(v.as_str())),
+            "title" | "body" | "caption" | "source" | "url" | "description" | "slug" | "format" | "state" | "date" => (k.as_str(), Value::from(v.as_str())),
+            _ => continue,
+        };
+        fields.insert(field.to_owned(), value);
+    }
+}
+
+fn new_post(state: &mut State, blog: &str, incoming: &Incoming) -> Result<u64, Reply> {
+    let r#type = incoming.param("type").unwrap_or("text");
+    let required = match r#type {
+        "text" => "body",
+        "photo" => "source",
+        "quote" => "quote",
+        "link" => "url",
+        "chat" => "conversation",
+        "audio" => "external_url",
+        "video" => "embed",
+        _ => return Err(Reply::error(400, "invalid post type")),
+    };
+    // photos may be uploaded as `data[i]` files instead
+    let files = incoming.uploads.iter()
+        .filter(|upload| upload.name.starts_with("data["))
+        .map(|upload| json!({ "original_size": { "url": format!("https://media.fake/{}/{}", upload.name, upload.data.len()) } }))
+        .collect::<Vec<_>>();
+    if incoming.param(required).is_none() && (r#type != "photo" || files.is_empty()) { return Err(Reply::error(400, &format!("{} is required", required))); }
+
+    let mut fields = Map::new();
+    fields.insert("type".to_owned(), Value::from(r#type));
+    if !files.is_empty() { fields.insert("photos".to_owned(), Value::from(files)); }
+    post_fields(incoming, &mut fields);
+    let post = serde_json::from_value::<Post>(Value::Object(fields)).map_err(|e| Reply::error(400, &e.to_string()))?;
+
+    Ok(insert(state, blog, post))
+}
+
+fn edit_post(state: &mut State, blog: &str, incoming: &Incoming) -> Result<u64, Reply> {
+    let id = incoming.number("id").ok_or_else(|| Reply::error(400, "id is required"))?;
+    let fake_blog = state.blog_mut(blog).ok_or_else(|| Reply::error(404, "blog not found"))?;
+
+    for posts in fake_blog.all_mut() {
+        if let Some(post) = posts.iter_mut().find(|post| post.id == id) {
+            let mut fields = match serde_json::to_value(&*post) {
+                Ok(Value::Object(fields)) => fields,
+                _ => return Err(Reply::error(500, "unserializable post")),
+            };
+            post_fields(incoming, &mut fields);
+            *post = serde_json::from_value(Value::Object(fields)).map_err(|e| Reply::error(400, &e.to_string()))?;
+
+            return Ok(id);
+        }
+    }
+
+    Err(Reply::error(404, "post not found"))
+}
+
+fn reblog_post(state: &mut State, blog: &str, incoming: &Incoming) -> Result<u64, Reply> {
+    let (id, reblog_key) = match (incoming.number("id"), incoming.param("reblog_key")) {
+        (Some(id), Some(reblog_key)) => (id, reblog_key),
+        _ => return Err(Reply::error(400, "id and reblog_key are required")),
+    };
+    let mut post = match state.post(id) {
+        Some((_, post)) if post.reblog_key == reblog_key => post.clone(),
+        Some(_) => return Err(Reply::error(400, "wrong reblog_key")),
+        None => return Err(Reply::error(404, "post not found")),
+    };
+
+    post.extra.insert("reblogged_from_id".to_owned(), Value::from(id.to_string()));
+    post.extra.insert("reblogged_from_name".to_owned(), Value::from(post.blog_name.clone()));
+    if let Some(comment) = incoming.param("comment") { post.extra.insert("comment".to_owned(), Value::from(comment)); }
+    post.id = 0;
+    post.blog_name = String::new();
+    post.reblog_key = String::new();
+    post.post_url = String::new();
+    post.timestamp = 0;
+    post.note_count = 0;
+    post.state = incoming.param("state").unwrap_or("published").to_owned();
+
+    Ok(insert(state, blog, post))
+}
+
+/// Create or reblog an NPF post, with its media uploaded as the parts after the `json` one.
+fn npf_post(state: &mut State, blog: &str, incoming: &Incoming) -> Result<u64, Reply> {
+    let mut uploads = incoming.uploads.iter().collect::<Vec<_>>();
+    let json = match (&incoming.json, uploads.first()) {
+        (Some(json), _) => json.clone(),
+        (None, Some(upload)) if upload.name == "json" && upload.content_type.as_deref() == Some("application/json") => {
+            let json = serde_json::from_slice(&uploads.remove(0).data).map_err(|e| Reply::error(400, &e.to_string()))?;
+            if uploads.is_empty() { return Err(Reply::error(400, "a multipart body must upload media")); }
+
+            json
+        }
+        _ => return Err(Reply::error(400, "a JSON body or a first part named json is required")),
+    };
+
+    let mut content = json["content"].as_array().cloned().ok_or_else(|| Reply::error(400, "content is required"))?;
+    for media_object in content.iter_mut().flat_map(media_objects) {
+        let identifier = match media_object["identifier"].as_str() {
+            Some(identifier) => identifier.to_owned(),
+            None => continue,
+        };
+        let upload = uploads.iter()
+            .position(|upload| upload.name == identifier)
+            .map(|i| uploads.remove(i))
+            .ok_or_else(|| Reply::error(400, &format!("no part for the media identifier {}", identifier)))?;
+        if upload.file_name.is_none() || upload.content_type.as_deref() != media_object["type"].as_str() {
+            return Err(Reply::error(400, &format!("the part {} must be a file of the type of its media", identifier)));
+        }
+
+        let media_object = media_object.as_object_mut().expect("media objects are objects");
+        media_object.remove("identifier");
+        media_object.insert("url".to_owned(), Value::from(format!("https://media.fake/{}/{}", identifier, upload.data.len())));
+    }
+    if let Some(upload) = uploads.first() { return Err(Reply::error(400, &format!("the part {} is not referenced by any block", upload.name))); }
+
+    let body = content.iter()
+        .filter(|block| block["type"] == "text")
+        .filter_map(|block| block["text"].as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut fields = Map::new();
+    fields.insert("type".to_owned(), Value::from("text"));
+    fields.insert("body".to_owned(), Value::from(body));
+    fields.insert("content".to_owned(), Value::from(content));
+    if let Some(tags) = json["tags"].as_str() { fields.insert("tags".to_owned(), Value::from(tags.split(',').filter(|tag| !tag.is_empty()).collect::<Vec<_>>())); }
+    for field in &["layout", "state", "slug"] {
+        if let Some(value) = json.get(*field) { fields.insert((*field).to_owned(), value.clone()); }
+    }
+    let mut post = serde_json::from_value::<Post>(Value::Object(fields)).map_err(|e| Reply::error(400, &e.to_string()))?;
+
+    if let Some(parent) = json["parent_post_id"].as_str() {
+        let id = parent.parse().map_err(|_| Reply::error(400, "invalid parent_post_id"))?;
+        match state.post(id) {
+            Some((_, parent)) if json["reblog_key"] == parent.reblog_key.as_str() => (),
+            Some(_) => return Err(Reply::error(400, "wrong reblog_key")),
+            None => return Err(Reply::error(404, "post not found")),
+        }
+        post.extra.insert("reblogged_from_id".to_owned(), Value::from(parent));
+    }
+
+    Ok(insert(state, blog, post))
+}
+
+/// The media objects of an image, audio or video block.
+fn media_objects(block: &mut Value) -> Vec<&mut Value> {
+    match block.get_mut("media") {
+        Some(Value::Array(media)) => media.iter_mut().collect(),
+        Some(media @ Value::Object(_)) => vec![media],
+        _ => vec![],
+    }
+}
+
+/// File a new post according to its state.
+fn insert(state: &mut State, blog: &str, post: Post) -> u64 {
+    let blog = state.blog(blog).map(|fake_blog| fake_blog.info.name.clone()).unwrap_or_else(|| blog.to_owned());
+    let post = state.new_post(&blog, post);
+    let id = post.id;
+    let fake_blog = state.add_blog(&blog);
+
+    match post.state.as_str() {
+        "queue" | "queued" => fake_blog.queue.insert(0, post),
+        "draft" => fake_blog.drafts.insert(0, post),
+        _ => fake_blog.posts.insert(0, post),
+    }
+
+    id
+}
+
+/// The url an OAuth signature covers for `incoming`.
+fn signed_url(shared: &Shared, incoming: &Incoming) -> String { format!("{}{}", shared.url, incoming.path.trim_start_matches('/')) }
+
+fn signer(shared: &Shared, incoming: &Incoming) -> Signer {
+    lock(&shared.auth).signer(&incoming.method, &signed_url(shared, incoming), &incoming.params, incoming.authorization.as_deref())
+}
+
+fn require_user(shared: &Shared, incoming: &Incoming) -> Result<(), Reply> {
+    match signer(shared, incoming) {
+        Signer::User => Ok(()),
+        _ => Err(Reply::error(401, "invalid or missing OAuth credentials")),
+    }
+}
+
+fn require_api_key(shared: &Shared, incoming: &Incoming) -> Result<(), Reply> {
+    if incoming.param("api_key") == Some(lock(&shared.auth).consumer_key.as_str()) { return Ok(()); }
+
+    match signer(shared, incoming) {
+        Signer::User | Signer::Consumer => Ok(()),
+        _ => Err(Reply::error(401, "invalid or missing api_key")),
+    }
+}
+
+fn oauth_params(incoming: &Incoming) -> Map<String, Value> {
+    incoming.authorization
+        .as_deref()
+        .and_then(parse_header)
+        .map(|oauth| oauth.into_iter().map(|(k, v)| (k, Value::from(v))).collect())
+        .unwrap_or_default()
+}
+
+fn request_token(shared: &Shared, incoming: &Incoming) -> Reply {
+    if signer(shared, incoming) != Signer::Consumer { return Reply::error(401, "invalid consumer signature"); }
+
+    let oauth = oauth_params(incoming);
+    let callback = oauth.get("oauth_callback").and_then(Value::as_str).map(str::to_owned);
+    let (key, secret) = (random_token(), random_token());
+    lock(&shared.auth).request_tokens.insert(key.clone(), (secret.clone(), callback, None));
+
+    Reply::text(200, "application/x-www-form-urlencoded", format!("oauth_token={}&oauth_token_secret={}&oauth_callback_confirmed=true", key, secret))
+}
+
+/// The user approves the app right away.
+fn authorize(shared: &Shared, incoming: &Incoming) -> Reply {
+    let key = incoming.param("oauth_token").unwrap_or_default();
+    let verifier = random_token();
+    let callback = match lock(&shared.auth).request_tokens.get_mut(key) {
+        Some((_, callback, pending)) => {
+            *pending = Some(verifier.clone());
+            callback.clone()
+        }
+        None => return Reply::error(400, "unknown oauth_token"),
+    };
+
+    match callback.and_then(|callback| Url::parse(&callback).ok()) {
+        Some(mut callback) => {
+            callback.query_pairs_mut()
+                .append_pair("oauth_token", key)
+                .append_pair("oauth_verifier", &verifier);
+
+            Reply::redirect(callback)
+        }
+        None => Reply::text(200, "text/plain", verifier),
+    }
+}
+
+fn access_token(shared: &Shared, incoming: &Incoming) -> Reply {
+    let oauth = oauth_params(incoming);
+    let key = oauth.get("oauth_token").and_then(Value::as_str).unwrap_or_default();
+    let verifier = oauth.get("oauth_verifier").and_then(Value::as_str);
+
+    let authorized = lock(&shared.auth).request_tokens
+        .get(key)
+        .is_some_and(|(_, _, pending)| pending.is_some() && pending.as_deref() == verifier);
+    if !authorized || signer(shared, incoming) != Signer::Consumer { return Reply::error(401, "invalid request token or verifier"); }
+
+    let mut auth = lock(&shared.auth);
+    auth.request_tokens.remove(key);
+    auth.revoked = false;
+
+    Reply::text(200, "application/x-www-form-urlencoded", format!("oauth_token={}&oauth_token_secret={}", auth.access_key, auth.access_secret))
+}
+
+/// The user approves the app right away.
+fn oauth2_authorize(shared: &Shared, incoming: &Incoming) -> Reply {
+    if incoming.param("client_id") != Some(lock(&shared.auth).consumer_key.as_str()) || incoming.param("response_type") != Some("code") {
+        return Reply::error(400, "invalid client_id or response_type");
+    }
+
+    let code = random_token();
+    lock(&shared.auth).codes.insert(code.clone(), incoming.param("scope").unwrap_or("basic").to_owned());
+
+    match incoming.param("redirect_uri").and_then(|redirect_uri| Url::parse(redirect_uri).ok()) {
+        Some(mut redirect_uri) => {
+            redirect_uri.query_pairs_mut().append_pair("code", &code);
+            if let Some(state) = incoming.param("state") { redirect_uri.query_pairs_mut().append_pair("state", state); }
+
+            Reply::redirect(redirect_uri)
+        }
+        None => Reply::text(200, "text/plain", code),
+    }
+}
+
+fn oauth2_token(shared: &Shared, incoming: &Incoming) -> Reply {
+    let oauth2_error = |status, error: &str, description: &str| Reply::json(status, &json!({ "error": error, "error_description": description }));
+
+    let mut auth = lock(&shared.auth);
+    if incoming.param("client_id") != Some(auth.consumer_key.as_str()) || incoming.param("client_secret") != Some(auth.consumer_secret.as_str()) {
+        return oauth2_error(401, "invalid_client", "unknown client or wrong secret");
+    }
+
+    let scope = match incoming.param("grant_type") {
+        Some("authorization_code") => auth.codes.remove(incoming.param("code").unwrap_or_default()),
+        Some("refresh_token") => auth.refresh_tokens.remove(incoming.param("refresh_token").unwrap_or_default()),
+        _ => return oauth2_error(400, "unsupported_grant_type", "unsupported grant_type"),
+    };
+    let scope = match scope {
+        Some(scope) => scope,
+        None => return oauth2_error(400, "invalid_grant", "invalid code or refresh token"),
+    };
+
+    let access_token = random_token();
+    auth.bearer_tokens.push(access_token.clone());
+    let mut token = json!({
+        "access_token": access_token,
+        "token_type": "bearer",
+        "expires_in": 2520,
+        "scope": scope,
+    });
+    if scope.split(' ').any(|scope| scope == "offline_access") {
+        let refresh_token = random_token();
+        auth.refresh_tokens.insert(refresh_token.clone(), scope);
+        token["refresh_token"] = Value::from(refresh_token);
+    }
+
+    Reply::json(200, &token)
+}
@@ -0,0 +1,133 @@
+// --- std ---
+use std::{
+    collections::BTreeMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+// --- custom ---
+use crate::{Blog, Follower, Post, User};
+
+/// Everything the fake Tumblr knows, seedable and inspectable through `FakeTumblr::state`.
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    /// The authenticated user, whose `blogs` are filled in from `blogs`.
+    pub user: User,
+    pub blogs: BTreeMap<String, FakeBlog>,
+    pub dashboard: Vec<Post>,
+    /// Posts liked by the user, most recent first.
+    pub likes: Vec<Post>,
+    pub following: Vec<Blog>,
+    next_id: u64,
+}
+
+/// A blog with its posts, newest first.
+#[derive(Debug, Clone, Default)]
+pub struct FakeBlog {
+    pub info: Blog,
+    pub posts: Vec<Post>,
+    pub queue: Vec<Post>,
+    pub drafts: Vec<Post>,
+    pub submissions: Vec<Post>,
+    /// Posts liked by the blog, most recent first.
+    pub likes: Vec<Post>,
+    pub followers: Vec<Follower>,
+    pub following: Vec<Blog>,
+}
+
+impl State {
+    /// A user named `name` with a primary blog of the same name.
+    pub fn new(name: &str) -> State {
+        let mut state = State::default();
+        state.user.name = name.to_owned();
+        state.add_blog(name).info.primary = Some(true);
+
+        state
+    }
+
+    /// Add a blog of the user, or get it if it exists.
+    pub fn add_blog(&mut self, name: &str) -> &mut FakeBlog {
+        self.blogs.entry(name.to_owned()).or_insert_with(|| FakeBlog {
+            info: Blog {
+                name: name.to_owned(),
+                title: name.to_owned(),
+                url: format!("https://{}.tumblr.com/", name),
+                uuid: format!("t:{}", name),
+                admin: Some(true),
+                ..Blog::default()
+            },
+            ..FakeBlog::default()
+        })
+    }
+
+    /// Publish `post` on `blog`, filling in its id, blog, reblog key and timestamp when unset. Returns its id.
+    pub fn add_post(&mut self, blog: &str, post: Post) -> u64 {
+        let post = self.new_post(blog, post);
+        let id = post.id;
+        self.add_blog(blog).posts.insert(0, post);
+
+        id
+    }
+
+    /// Find a post of any blog by id, with the name of its blog.
+    pub fn post(&self, id: u64) -> Option<(&str, &Post)> {
+        self.blogs.iter().find_map(|(name, blog)| blog.all().find(|post| post.id == id).map(|post| (name.as_str(), post)))
+    }
+
+    pub fn blog(&self, identifier: &str) -> Option<&FakeBlog> { self.blogs.get(blog_name(identifier)) }
+
+    pub(super) fn blog_mut(&mut self, identifier: &str) -> Option<&mut FakeBlog> { self.blogs.get_mut(blog_name(identifier)) }
+
+    pub(super) fn user(&self) -> User {
+        User {
+            likes: self.likes.len() as u64,
+            following: self.following.len() as u64,
+            blogs: self.blogs.values().map(|blog| blog.info()).collect(),
+            ..self.user.clone()
+        }
+    }
+
+    pub(super) fn new_post(&mut self, blog: &str, mut post: Post) -> Post {
+        if post.id == 0 {
+            self.next_id += 1;
+            post.id = self.next_id;
+        } else { self.next_id = self.next_id.max(post.id); }
+        post.id_string = post.id.to_string();
+        if post.blog_name.is_empty() { post.blog_name = blog.to_owned(); }
+        if post.reblog_key.is_empty() { post.reblog_key = format!("reblog-{}", post.id); }
+        if post.timestamp == 0 { post.timestamp = now(); }
+        if post.state.is_empty() { post.state = "published".to_owned(); }
+        if post.post_url.is_empty() { post.post_url = format!("https://{}.tumblr.com/post/{}", post.blog_name, post.id); }
+
+        post
+    }
+}
+
+impl FakeBlog {
+    pub(super) fn info(&self) -> Blog {
+        Blog {
+            posts: Some(self.posts.len() as u64),
+            likes: Some(self.likes.len() as u64),
+            followers: Some(self.followers.len() as u64),
+            ..self.info.clone()
+        }
+    }
+
+    pub(super) fn all(&self) -> impl Iterator<Item = &Post> {
+        self.posts.iter()
+            .chain(self.queue.iter())
+            .chain(self.drafts.iter())
+            .chain(self.submissions.iter())
+    }
+
+    pub(super) fn all_mut(&mut self) -> impl Iterator<Item = &mut Vec<Post>> {
+        vec![&mut self.posts, &mut self.queue, &mut self.drafts, &mut self.submissions].into_iter()
+    }
+}
+
+/// `name`, `name.tumblr.com` and `t:uuid` style identifiers all name the blog `name`.
+fn blog_name(identifier: &str) -> &str {
+    identifier
+        .trim_end_matches(".tumblr.com")
+        .trim_start_matches("t:")
+}
+
+pub(super) fn now() -> u64 { SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() }
@@ -0,0 +1,60 @@
+// --- std ---
+use std::{env, fs, path::PathBuf, process};
+// --- external ---
+use rumblr::{
+    Cassette,
+    TumblrClient,
+    testing::{self, FakeTumblr},
+};
+
+fn cassette_path(name: &str) -> PathBuf { env::temp_dir().join(format!("rumblr-{}-{}.json", name, process::id())) }
+
+#[test]
+fn record_and_replay() {
+    let path = cassette_path("record-and-replay");
+    let fake = FakeTumblr::start().unwrap();
+
+    let client = fake.client().record_cassette(&path).unwrap();
+    let recorded = client.get_blog_info("rumblr").unwrap();
+    client.follow_blog("staff.tumblr.com").unwrap();
+    client.verify_credentials().unwrap();
+
+    let cassette = fs::read_to_string(&path).unwrap();
+    for secret in &[testing::CONSUMER_KEY, testing::CONSUMER_SECRET, testing::ACCESS_KEY, testing::ACCESS_SECRET] {
+        assert!(!cassette.contains(secret), "{} leaked into the cassette", secret);
+    }
+    assert_eq!(Cassette::load(&path).unwrap().interactions.len(), 3);
+
+    // signed with other keys, hence another nonce, timestamp and signature, and a fake which is gone
+    let url = fake.api_url();
+    drop(fake);
+    let client = TumblrClient::with_keys("other", "keys", "entirely", "")
+        .api_base_url(&url)
+        .unwrap()
+        .replay_cassette(&path)
+        .unwrap();
+    assert_eq!(client.verify_credentials().unwrap().following, 1);
+    assert_eq!(client.get_blog_info("rumblr").unwrap().blog.name, recorded.blog.name);
+    client.follow_blog("staff.tumblr.com").unwrap();
+
+    assert!(client.follow_blog("staff.tumblr.com").is_err(), "each response is replayed once");
+    assert!(client.follow_blog("other.tumblr.com").is_err(), "params must match");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn redacted_tokens() {
+    let path = cassette_path("redacted-tokens");
+    let fake = FakeTumblr::start().unwrap();
+
+    let client = fake.consumer_client().record_cassette(&path).unwrap();
+    let request_token = client.request_token(None).unwrap();
+    let oauth_verifier = reqwest::get(&request_token.authorize_url()).unwrap().text().unwrap();
+    client.access_token(&request_token, &oauth_verifier).unwrap();
+
+    let cassette = fs::read_to_string(&path).unwrap();
+    assert!(!cassette.contains(&oauth_verifier));
+    assert!(!cassette.contains(testing::ACCESS_SECRET));
+    assert!(cassette.contains("oauth_token_secret=REDACTED"));
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,656 @@
+// --- std ---
+use std::{
+    env,
+    fs,
+    net::TcpStream,
+    process,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+// --- external ---
+use chrono::TimeZone;
+use rumblr::{
+    AudioBlock,
+    Auth,
+    BlogInfo,
+    ContentBlock,
+    Endpoint,
+    FollowBlog,
+    GetBlogInfo,
+    GetBlogLikesOptionalParams,
+    GetBlogPostsOptionalParams,
+    GetUserLikesOptionalParams,
+    ImageBlock,
+    LegacyPostOptionalParams,
+    Media,
+    Method,
+    NpfContent,
+    NpfPostOptionalParams,
+    Post,
+    PostAction,
+    PostContent,
+    PostMatch,
+    PostState,
+    PostType,
+    RequestBody,
+    RetryPolicy,
+    Scope,
+    TextBlock,
+    TumblrClient,
+    TumblrClientError,
+    UserInfo,
+    Utc,
+    WritePolicy,
+    testing::{FakeTumblr, Failure, State},
+};
+
+fn text_post(body: &str, tags: &[&str], timestamp: u64) -> Post {
+    Post {
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        timestamp,
+        content: PostContent::Text {
+            title: None,
+            body: body.to_owned(),
+        },
+        ..Post::default()
+    }
+}
+
+fn body(post: &Post) -> &str {
+    match post.content {
+        PostContent::Text { ref body, .. } => body,
+        _ => panic!("not a text post: {:?}", post),
+    }
+}
+
+fn fast_retries(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy::new()
+        .max_attempts(max_attempts)
+        .base_delay(Duration::from_millis(10))
+        .jitter(false)
+}
+
+/// A fake whose `rumblr` blog has `n` text posts, the newest first.
+fn fake_with_posts(n: u64) -> FakeTumblr {
+    let mut state = State::new("rumblr");
+    for i in 1..=n { state.add_post("rumblr", text_post(&format!("post {}", i), if i % 2 == 0 { &["even"] } else { &[] }, 1_000 + i)); }
+
+    FakeTumblr::with_state(state).unwrap()
+}
+
+#[test]
+fn user_info() {
+    let fake = FakeTumblr::start().unwrap();
+    fake.state().add_blog("side");
+
+    let user = fake.client().verify_credentials().unwrap();
+    assert_eq!(user.name, "rumblr");
+    assert_eq!(user.blogs.iter().map(|blog| blog.name.as_str()).collect::<Vec<_>>(), ["rumblr", "side"]);
+    assert_eq!(fake.requests()[0].path, "/v2/user/info");
+}
+
+#[test]
+fn revoked_access() {
+    let fake = FakeTumblr::start().unwrap();
+    let unauthorized = Arc::new(Mutex::new(vec![]));
+    let client = {
+        let unauthorized = unauthorized.clone();
+        fake.client().on_unauthorized(move |url| unauthorized.lock().unwrap().push(url.to_owned()))
+    };
+
+    client.verify_credentials().unwrap();
+    fake.revoke_access();
+    assert!(client.verify_credentials().unwrap_err().is_unauthorized());
+    assert_eq!(*unauthorized.lock().unwrap(), [format!("{}user/info", fake.api_url())]);
+}
+
+#[test]
+fn wrong_keys() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = fake.configure(TumblrClient::with_keys(rumblr::testing::CONSUMER_KEY, "wrong", rumblr::testing::ACCESS_KEY, rumblr::testing::ACCESS_SECRET));
+
+    assert!(client.verify_credentials().unwrap_err().is_unauthorized());
+    assert!(client.get_blog_info("rumblr").is_ok(), "api_key endpoints only need the consumer key");
+}
+
+#[test]
+fn blog_posts() {
+    let fake = fake_with_posts(5);
+    let client = fake.client();
+
+    let info = client.get_blog_info("rumblr.tumblr.com").unwrap();
+    assert_eq!(info.blog.posts, Some(5));
+
+    let posts = client.get_blog_posts("rumblr", Some(GetBlogPostsOptionalParams::new().tag("even").limit(1))).unwrap();
+    assert_eq!(posts.total_posts, Some(2));
+    assert_eq!(posts.posts.len(), 1);
+    assert_eq!(body(&posts.posts[0]), "post 4");
+
+    assert!(client.get_blog_info("nobody").unwrap_err().to_string().contains("404"));
+}
+
+#[test]
+fn pagination() {
+    let fake = fake_with_posts(45);
+    let client = fake.client();
+
+    let posts = client.blog_posts_iter("rumblr", None).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(posts.len(), 45);
+    assert_eq!(body(&posts[0]), "post 45");
+    assert_eq!(body(&posts[44]), "post 1");
+    assert_eq!(fake.requests().len(), 3);
+
+    // paging keeps going from the caller's offset
+    let posts = client.blog_posts_iter("rumblr", Some(GetBlogPostsOptionalParams::new().offset(40))).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(posts.iter().map(body).collect::<Vec<_>>(), ["post 5", "post 4", "post 3", "post 2", "post 1"]);
+
+    // in pages of the caller's own limit
+    let sent = fake.requests().len();
+    let posts = client.blog_posts_iter("rumblr", Some(GetBlogPostsOptionalParams::new().limit(5).offset(30))).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(posts.len(), 15);
+    let pages = fake.requests().split_off(sent);
+    assert_eq!(pages.iter().map(|request| request.param("offset")).collect::<Vec<_>>(), [Some("30"), Some("35"), Some("40")]);
+    assert!(pages.iter().all(|request| request.param("limit") == Some("5")));
+
+    assert!(matches!(client.blog_posts_iter("rumblr", Some(GetBlogPostsOptionalParams::new().limit(50))).next(), Some(Err(TumblrClientError::InvalidInput { .. }))));
+}
+
+#[test]
+fn likes_pagination() {
+    let mut state = State::new("rumblr");
+    for i in 1..=45 { state.add_post("rumblr", text_post(&format!("post {}", i), &[], 1_000 + i)); }
+    state.likes = state.blog("rumblr").unwrap().posts.iter().map(|post| Post { liked_timestamp: Some(post.timestamp + 1_000), ..post.clone() }).collect();
+    let fake = FakeTumblr::with_state(state).unwrap();
+    let client = fake.client();
+
+    // later pages go by `before` alone, which Tumblr rejects alongside `offset`
+    let likes = client.user_likes_iter(Some(GetUserLikesOptionalParams::default().offset(0))).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(likes.len(), 45);
+    assert_eq!(body(&likes[44]), "post 1");
+
+    // and forward by `after` if that's where the caller started
+    let after = Utc.timestamp_opt(2_010, 0).unwrap();
+    let likes = client.user_likes_iter(Some(GetUserLikesOptionalParams::default().after(after))).collect::<Result<Vec<_>, _>>().unwrap();
+    let mut bodies = likes.iter().map(body).collect::<Vec<_>>();
+    bodies.sort_by_key(|body| body[5..].parse::<u32>().unwrap());
+    assert_eq!(bodies, (11..=45).map(|i| format!("post {}", i)).collect::<Vec<_>>());
+}
+
+#[test]
+fn follow_and_like() {
+    let fake = fake_with_posts(1);
+    let client = fake.client();
+    let post = fake.state().blog("rumblr").unwrap().posts[0].clone();
+
+    client.follow_blog("staff.tumblr.com").unwrap();
+    assert_eq!(client.get_user_following(None).unwrap().blogs[0].name, "staff");
+    client.unfollow_blog("staff.tumblr.com").unwrap();
+    assert_eq!(client.get_user_following(None).unwrap().total_blogs, 0);
+
+    client.like_post(&post.id_string, &post.reblog_key).unwrap();
+    let likes = client.user_likes_iter(None).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(likes.len(), 1);
+    assert_eq!(likes[0].id, post.id);
+    assert!(client.like_post(&post.id_string, "wrong").is_err());
+    client.unlike_post(&post.id_string, &post.reblog_key).unwrap();
+    assert_eq!(client.get_user_likes(None).unwrap().liked_count, 0);
+}
+
+#[test]
+fn legacy_posts() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = fake.client();
+
+    let id = client.legacy_post(
+        "rumblr",
+        PostAction::New,
+        PostType::Text { title: Some("Title".to_owned()), body: "Hello".to_owned() },
+        Some(LegacyPostOptionalParams::new().tags(&["a", "b"])),
+    ).unwrap().id;
+    let post = fake.state().post(id).unwrap().1.clone();
+    assert_eq!(body(&post), "Hello");
+    assert_eq!(post.tags, ["a", "b"]);
+
+    let edit = id.to_string();
+    client.legacy_post("rumblr", PostAction::Edit(edit.clone()), PostType::Text { title: None, body: "Edited".to_owned() }, None).unwrap();
+    assert_eq!(body(fake.state().post(id).unwrap().1), "Edited");
+
+    fake.state().add_blog("side");
+    let reblog = client.legacy_post(
+        "side",
+        PostAction::Reblog {
+            id: edit.clone(),
+            reblog_key: post.reblog_key.clone(),
+            comment: "Nice".to_owned(),
+            native_inline_images: false,
+        },
+        PostType::Text { title: None, body: "Edited".to_owned() },
+        None,
+    ).unwrap().id;
+    assert_eq!(fake.state().post(reblog).unwrap().0, "side");
+
+    client.delete_post("rumblr", &edit).unwrap();
+    assert!(fake.state().post(id).is_none());
+
+    let draft = client.legacy_post("rumblr", PostAction::New, PostType::Text { title: None, body: "Later".to_owned() }, Some(LegacyPostOptionalParams::new().state(PostState::Draft))).unwrap().id;
+    assert_eq!(client.drafts_iter("rumblr", None).next().unwrap().unwrap().id, draft);
+}
+
+#[test]
+fn photo_uploads_are_signed() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = fake.client();
+    let path = env::temp_dir().join(format!("rumblr-photo-{}.png", process::id()));
+    fs::write(&path, [1, 2, 3]).unwrap();
+
+    // the fake rejects signatures which don't cover the text fields of the multipart body
+    let id = client.legacy_post(
+        "rumblr",
+        PostAction::New,
+        PostType::Photo { caption: Some("Signed & sent".to_owned()), link: None, source: None, data: Some(vec![path.to_string_lossy().into_owned()]), data64: None },
+        Some(LegacyPostOptionalParams::new().tags(&["a b", "c"])),
+    ).unwrap().id;
+    fs::remove_file(&path).unwrap();
+
+    let post = fake.state().post(id).unwrap().1.clone();
+    assert_eq!(post.tags, ["a b", "c"]);
+    match post.content {
+        PostContent::Photo { ref caption, ref photos } => {
+            assert_eq!(caption, "Signed & sent");
+            assert_eq!(photos[0].original_size.url, "https://media.fake/data[0]/3");
+        }
+        _ => panic!("not a photo post: {:?}", post),
+    }
+}
+
+#[test]
+fn retries() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = fake.client().retry_policy(fast_retries(3));
+
+    fake.fail("user/info", Failure::Status(500), 1);
+    fake.fail("user/info", Failure::TooManyRequests(None), 1);
+    client.verify_credentials().unwrap();
+    assert_eq!(fake.requests().len(), 3);
+
+    fake.fail("user/info", Failure::Status(503), 3);
+    let e = client.verify_credentials().unwrap_err();
+    assert!(e.to_string().contains("503"), "{}", e);
+}
+
+#[test]
+fn writes_are_not_resent() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = fake.client().retry_policy(fast_retries(3));
+
+    fake.fail("/post", Failure::Status(502), 1);
+    assert!(client.legacy_post("rumblr", PostAction::New, PostType::Text { title: None, body: "Once".to_owned() }, None).is_err());
+    assert_eq!(fake.requests().len(), 1);
+
+    // unlike idempotent ones
+    for method in &[Method::PUT, Method::DELETE] {
+        fake.fail("user/nothing", Failure::Status(502), 1);
+        let sent = fake.requests().len();
+        let e = client.request_json::<UserInfo>(method.clone(), "user/nothing", &[], RequestBody::Empty).unwrap_err();
+        assert!(e.to_string().contains("404"), "{}", e);
+        assert_eq!(fake.requests().len(), sent + 2, "{}", method);
+    }
+}
+
+#[test]
+fn verified_writes_are_not_duplicated() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = fake.client()
+        .retry_policy(fast_retries(3))
+        .write_policy(WritePolicy::Verify(PostMatch::Marker("rumblr-test-marker".to_owned())));
+
+    fake.fail("rumblr/post", Failure::AfterProcessing(500), 1);
+    let id = client.legacy_post("rumblr", PostAction::New, PostType::Text { title: None, body: "Exactly once".to_owned() }, None).unwrap().id;
+
+    let state = fake.state();
+    let posts = &state.blog("rumblr").unwrap().posts;
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0].id, id);
+}
+
+#[test]
+fn timeout() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = fake.configure(TumblrClient::builder().timeout(Duration::from_millis(200)).build().unwrap())
+        .retry_policy(RetryPolicy::never());
+
+    fake.fail("blog/rumblr/info", Failure::Delay(Duration::from_secs(2)), 1);
+    let e = client.get_blog_info("rumblr").unwrap_err();
+    assert!(e.to_string().to_lowercase().contains("timed out"), "{}", e);
+}
+
+#[test]
+fn oauth() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = fake.consumer_client();
+
+    let request_token = client.request_token(None).unwrap();
+    let oauth_verifier = reqwest::get(&request_token.authorize_url()).unwrap().text().unwrap();
+    let client = client.access_token(&request_token, &oauth_verifier).unwrap();
+    assert_eq!(client.verify_credentials().unwrap().name, "rumblr");
+
+    assert!(fake.consumer_client().access_token(&request_token, &oauth_verifier).is_err(), "request tokens are single use");
+}
+
+#[test]
+fn oauth_loopback() {
+    let fake = FakeTumblr::start().unwrap();
+
+    let mut browser = None;
+    let client = fake.consumer_client()
+        .oauth_loopback(|url| {
+            let url = url.to_owned();
+            browser = Some(thread::spawn(move || {
+                let http = reqwest::Client::builder().redirect(reqwest::RedirectPolicy::none()).build().unwrap();
+                let callback = http.get(&url).send().unwrap().headers()[reqwest::header::LOCATION].to_str().unwrap().to_owned();
+                let (listener, _) = callback.split_once('?').unwrap();
+
+                // neither an idle connection, a verifier without the request token nor a denial of another token ends the wait
+                let _idle = TcpStream::connect(listener.trim_start_matches("http://").split('/').next().unwrap()).unwrap();
+                let forged = http.get(&format!("{}?oauth_verifier=forged", listener)).send().unwrap().status();
+                let stray = http.get(&format!("{}?oauth_token=stale", listener)).send().unwrap().status();
+                http.get(&callback).send().unwrap();
+
+                [forged.as_u16(), stray.as_u16()]
+            }));
+        }, Duration::from_secs(10))
+        .unwrap();
+    assert_eq!(client.verify_credentials().unwrap().name, "rumblr");
+    assert_eq!(browser.unwrap().join().unwrap(), [400, 400]);
+}
+
+#[test]
+fn oauth_loopback_timeout() {
+    let fake = FakeTumblr::start().unwrap();
+    fake.fail("oauth/access_token", Failure::Delay(Duration::from_secs(5)), 1);
+
+    let started = Instant::now();
+    let e = fake.consumer_client()
+        .oauth_loopback(|url| {
+            let url = url.to_owned();
+            thread::spawn(move || reqwest::get(&url));
+        }, Duration::from_secs(1))
+        .unwrap_err();
+    assert!(e.to_string().contains("timed out"), "{}", e);
+    assert!(started.elapsed() < Duration::from_secs(3), "{:?}", started.elapsed());
+}
+
+#[test]
+fn oauth2() {
+    let fake = FakeTumblr::start().unwrap();
+    let refreshed = Arc::new(Mutex::new(0));
+    let client = {
+        let refreshed = refreshed.clone();
+        fake.consumer_client().on_token_refresh(move |_| *refreshed.lock().unwrap() += 1)
+    };
+
+    let url = client.oauth2_authorize_url(&[Scope::Basic, Scope::OfflineAccess], "xyz", None).unwrap();
+    let code = reqwest::get(&url).unwrap().text().unwrap();
+    let client = client.oauth2_exchange_code(&code, None).unwrap();
+    let token = client.get_oauth2_token().unwrap();
+    assert!(token.refresh_token.is_some());
+    assert_eq!(client.verify_credentials().unwrap().name, "rumblr");
+
+    fake.expire_oauth2_tokens();
+    assert_eq!(client.verify_credentials().unwrap().name, "rumblr");
+    assert_eq!(*refreshed.lock().unwrap(), 1);
+    assert_ne!(client.get_oauth2_token().unwrap().access_token, token.access_token);
+
+    // requests refused at once share a single refresh
+    fake.expire_oauth2_tokens();
+    let client = Arc::new(client);
+    let workers = (0..4)
+        .map(|_| {
+            let client = client.clone();
+            thread::spawn(move || client.verify_credentials().map(|user| user.name))
+        })
+        .collect::<Vec<_>>();
+    for worker in workers { assert_eq!(worker.join().unwrap().unwrap(), "rumblr"); }
+    assert_eq!(*refreshed.lock().unwrap(), 2);
+
+    assert!(fake.consumer_client().oauth2_exchange_code(&code, None).is_err(), "codes are single use");
+}
+
+#[test]
+fn verified_writes_refresh_oauth2_tokens() {
+    let fake = FakeTumblr::start().unwrap();
+    let refreshed = Arc::new(Mutex::new(0));
+    let client = {
+        let refreshed = refreshed.clone();
+        fake.consumer_client()
+            .write_policy(WritePolicy::Verify(PostMatch::BodyHash))
+            .on_token_refresh(move |_| *refreshed.lock().unwrap() += 1)
+    };
+
+    let url = client.oauth2_authorize_url(&[Scope::Write, Scope::OfflineAccess], "xyz", None).unwrap();
+    let code = reqwest::get(&url).unwrap().text().unwrap();
+    let client = client.oauth2_exchange_code(&code, None).unwrap();
+
+    fake.expire_oauth2_tokens();
+    let id = client.create_npf_post("rumblr", &[ContentBlock::Text(TextBlock::new("Exactly once"))], None).unwrap().id;
+    assert_eq!(*refreshed.lock().unwrap(), 1);
+    assert_eq!(fake.state().blog("rumblr").unwrap().posts.iter().map(|post| post.id).collect::<Vec<_>>(), [id]);
+}
+
+#[test]
+fn unsaved_oauth2_tokens() {
+    let fake = FakeTumblr::start().unwrap();
+    let errors = Arc::new(Mutex::new(vec![]));
+    let client = {
+        let errors = errors.clone();
+        let path = env::temp_dir().join(format!("rumblr-missing-{}", process::id())).join("rumblr.token");
+        fake.consumer_client().persist_oauth2_token(path.to_str().unwrap(), move |e| errors.lock().unwrap().push(e.kind()))
+    };
+
+    let url = client.oauth2_authorize_url(&[Scope::OfflineAccess], "xyz", None).unwrap();
+    let code = reqwest::get(&url).unwrap().text().unwrap();
+    let client = client.oauth2_exchange_code(&code, None).unwrap();
+    client.refresh_oauth2_token().unwrap();
+    assert_eq!(*errors.lock().unwrap(), [std::io::ErrorKind::NotFound]);
+}
+
+#[test]
+fn special_characters() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = fake.client();
+    let tags = ["my art", "c++", "a&b=c", "café 日本 🐱", "50%"];
+
+    let id = client.legacy_post(
+        "rumblr",
+        PostAction::New,
+        PostType::Text { title: Some("Q&A = fun?".to_owned()), body: "1 + 1 = 2 & more".to_owned() },
+        Some(LegacyPostOptionalParams::new().tags(&tags)),
+    ).unwrap().id;
+    let post = fake.state().post(id).unwrap().1.clone();
+    assert_eq!(post.tags, tags);
+    assert_eq!(body(&post), "1 + 1 = 2 & more");
+
+    for tag in &tags {
+        let posts = client.get_blog_posts("rumblr", Some(GetBlogPostsOptionalParams::new().tag(tag))).unwrap();
+        assert_eq!(posts.total_posts, Some(1), "{}", tag);
+    }
+}
+
+#[test]
+fn raw_requests() {
+    let fake = fake_with_posts(1);
+    let client = fake.client();
+
+    let info: UserInfo = client.request_json(Method::GET, "user/info", &[], RequestBody::Empty).unwrap();
+    assert_eq!(info.user.name, "rumblr");
+
+    // every repeated param is signed
+    let info: UserInfo = client.request_json(Method::GET, "user/info", &[("tags[]", "c"), ("tags[]", "a")], RequestBody::Empty).unwrap();
+    assert_eq!(info.user.name, "rumblr");
+
+    let info: BlogInfo = fake.consumer_client().request_json(Method::GET, "/blog/rumblr/info", &[], RequestBody::Empty).unwrap();
+    assert_eq!(info.blog.posts, Some(1));
+    assert_eq!(fake.requests().last().unwrap().param("api_key"), Some(rumblr::testing::CONSUMER_KEY));
+
+    let resp = client.request(Method::POST, "user/follow", &[], RequestBody::form(&[("url", "staff.tumblr.com"), ("x[]", "b"), ("x[]", "a")])).unwrap();
+    assert!(resp.status().is_success());
+    assert_eq!(fake.state().following[0].name, "staff");
+
+    let url = format!("{}blog/rumblr/posts", fake.api_url());
+    let resp = client.request_with(Auth::None, Method::GET, &url, &[("tag", "c++")], RequestBody::Empty).unwrap();
+    assert_eq!(resp.status().as_u16(), 401);
+    assert_eq!(fake.requests().last().unwrap().param("tag"), Some("c++"));
+
+    let e = client.request_json::<UserInfo>(Method::DELETE, "user/nothing", &[], RequestBody::Empty).unwrap_err();
+    assert!(e.to_string().contains("404"), "{}", e);
+}
+
+/// The posts of a blog, tagged `tag`, as raw JSON.
+struct Tagged<'a> {
+    blog_identifier: &'a str,
+    tag: &'a str,
+}
+
+impl Endpoint for Tagged<'_> {
+    type Response = serde_json::Value;
+
+    fn path(&self) -> String { format!("blog/{}/posts", self.blog_identifier) }
+
+    fn auth(&self) -> Auth { Auth::ApiKey }
+
+    fn params(&self) -> Vec<(&str, String)> { vec![("tag", self.tag.to_owned())] }
+}
+
+#[test]
+fn endpoints() {
+    let fake = fake_with_posts(2);
+    let client = fake.client();
+
+    let info = client.call(GetBlogInfo { blog_identifier: "rumblr".to_owned() }).unwrap();
+    assert_eq!(info.blog.posts, Some(2));
+
+    client.call(FollowBlog { url: "staff.tumblr.com".to_owned() }).unwrap();
+    assert_eq!(fake.state().following[0].name, "staff");
+
+    fake.fail("blog/rumblr/posts", Failure::Status(503), 1);
+    let posts = client.retry_policy(fast_retries(2)).call(Tagged { blog_identifier: "rumblr", tag: "c++" }).unwrap();
+    assert_eq!(posts["total_posts"], 0);
+    let requests = fake.requests();
+    assert_eq!(requests.len(), 4);
+    assert_eq!(requests[3].param("tag"), Some("c++"));
+    assert_eq!(requests[3].param("api_key"), Some(rumblr::testing::CONSUMER_KEY));
+}
+
+#[test]
+fn invalid_params() {
+    let fake = fake_with_posts(1);
+    let client = fake.client();
+    let invalid = |e: TumblrClientError| matches!(e, TumblrClientError::InvalidInput { .. });
+
+    assert!(invalid(client.get_blog_posts("rumblr", Some(GetBlogPostsOptionalParams::new().limit(21))).unwrap_err()));
+    assert!(invalid(client.get_blog_likes("rumblr", Some(GetBlogLikesOptionalParams::new().offset(20).before(Utc::now()))).unwrap_err()));
+    assert!(invalid(client.create_npf_post("rumblr", &[], Some(NpfPostOptionalParams::new().publish_on(Utc::now()))).unwrap_err()));
+    assert!(invalid(client.legacy_post("rumblr", PostAction::New, PostType::Text { title: None, body: "Hi".to_owned() }, Some(LegacyPostOptionalParams::new().tag("a,b"))).unwrap_err()));
+    assert!(fake.requests().is_empty());
+}
+
+#[test]
+fn queued_posts() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = Arc::new(fake.client());
+
+    // as a scheduler would persist them, then hand them to its workers
+    let queue = (0..4)
+        .map(|i| serde_json::to_string(&(
+            PostType::Text { title: None, body: format!("queued {}", i) },
+            LegacyPostOptionalParams::new().tags(&["queued"]).state(PostState::Draft),
+        )).unwrap())
+        .collect::<Vec<_>>();
+    let workers = queue.into_iter()
+        .map(|job| {
+            let client = client.clone();
+            thread::spawn(move || {
+                let (post_type, optional_params): (PostType, LegacyPostOptionalParams) = serde_json::from_str(&job).unwrap();
+                client.legacy_post("rumblr", PostAction::New, post_type, Some(optional_params)).unwrap().id
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for worker in workers {
+        let id = worker.join().unwrap();
+        let post = fake.state().post(id).unwrap().1.clone();
+        assert!(body(&post).starts_with("queued "));
+        assert_eq!(post.tags, ["queued"]);
+    }
+}
+
+#[test]
+fn verified_npf_posts_are_not_duplicated() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = fake.client()
+        .retry_policy(fast_retries(3))
+        .write_policy(WritePolicy::Verify(PostMatch::BodyHash));
+
+    fake.fail("rumblr/posts", Failure::AfterProcessing(500), 1);
+    let id = client.create_npf_post("rumblr", &[ContentBlock::Text(TextBlock::new("Exactly once"))], None).unwrap().id;
+
+    let posts = fake.state().blog("rumblr").unwrap().posts.clone();
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0].id, id);
+    assert_eq!(fake.requests().iter().map(|r| r.method.as_str()).collect::<Vec<_>>(), ["POST", "GET"]);
+
+    // a post without text has no body to hash
+    let requests = fake.requests().len();
+    let e = client.create_npf_post("rumblr", &[ContentBlock::Image(ImageBlock::default())], None).unwrap_err();
+    assert!(matches!(e, TumblrClientError::InvalidInput { .. }), "{}", e);
+    assert_eq!(fake.requests().len(), requests);
+}
+
+#[test]
+fn verified_unpublished_npf_posts_are_not_duplicated() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = fake.client()
+        .retry_policy(fast_retries(3))
+        .write_policy(WritePolicy::Verify(PostMatch::BodyHash));
+
+    let mut ids = vec![];
+    for &state in &[PostState::Draft, PostState::Queue, PostState::Private] {
+        fake.fail("rumblr/posts", Failure::AfterProcessing(500), 1);
+        let text = format!("Exactly once, as {:?}", state);
+        ids.push(client.create_npf_post("rumblr", &[ContentBlock::Text(TextBlock::new(&text))], Some(NpfPostOptionalParams::new().state(state))).unwrap().id);
+    }
+
+    let blog = fake.state().blog("rumblr").unwrap().clone();
+    assert_eq!(blog.drafts.iter().map(|post| post.id).collect::<Vec<_>>(), [ids[0]]);
+    assert_eq!(blog.queue.iter().map(|post| post.id).collect::<Vec<_>>(), [ids[1]]);
+    assert_eq!(blog.posts.iter().map(|post| post.id).collect::<Vec<_>>(), [ids[2]]);
+    assert_eq!(fake.requests().iter().map(|r| r.method.as_str()).collect::<Vec<_>>(), ["POST", "GET", "POST", "GET", "POST", "GET"]);
+}
+
+#[test]
+fn npf_posts() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = fake.client();
+
+    let id = client.create_npf_post("rumblr", &[ContentBlock::Text(TextBlock::new("Hello"))], Some(NpfPostOptionalParams::new().tags(&["a", "b"]))).unwrap().id;
+    let post = fake.state().post(id).unwrap().1.clone();
+    assert_eq!(post.blocks, [ContentBlock::Text(TextBlock::new("Hello"))]);
+    assert_eq!(post.tags, ["a", "b"]);
+
+    // the fake rejects media parts which don't match the identifiers of their blocks
+    let id = client.create_npf_post_with_media("rumblr", vec![
+        ContentBlock::Text(TextBlock::new("Two files")).into(),
+        NpfContent::Upload(ContentBlock::Image(ImageBlock::default()), Media::bytes(vec![1, 2, 3], "image/png").dimensions(1, 1)),
+        NpfContent::Upload(ContentBlock::Audio(AudioBlock::default()), Media::bytes(vec![4; 5], "audio/mpeg").file_name("song.mp3")),
+    ], None).unwrap().id;
+    let post = fake.state().post(id).unwrap().1.clone();
+    match (&post.blocks[1], &post.blocks[2]) {
+        (ContentBlock::Image(image), ContentBlock::Audio(audio)) => {
+            assert_eq!(image.media[0].url.as_deref(), Some("https://media.fake/media-0/3"));
+            assert_eq!(image.media[0].width, Some(1));
+            assert_eq!(audio.media.as_ref().and_then(|media| media.url.as_deref()), Some("https://media.fake/media-1/5"));
+        }
+        blocks => panic!("unexpected blocks: {:?}", blocks),
+    }
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "async")]
+
+// --- std ---
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+// --- external ---
+use futures::Stream;
+use rumblr::{
+    AsyncTumblrClient,
+    ContentBlock,
+    RetryPolicy,
+    Scope,
+    TextBlock,
+    Throttle,
+    TumblrClientError,
+    TumblrFuture,
+    WritePolicy,
+    testing::{FakeTumblr, Failure},
+};
+use tokio::runtime::Runtime;
+
+fn fast_retries(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy::new()
+        .max_attempts(max_attempts)
+        .base_delay(Duration::from_millis(10))
+        .jitter(false)
+}
+
+/// Drive `future` to completion on a runtime of its own.
+fn run<T: Send + 'static>(future: TumblrFuture<T>) -> Result<T, TumblrClientError> { Runtime::new().unwrap().block_on(future) }
+
+#[test]
+fn user_info() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = AsyncTumblrClient::from(fake.client());
+
+    assert_eq!(run(client.get_user_info()).unwrap().user.name, "rumblr");
+    assert_eq!(run(client.verify_credentials()).unwrap().name, "rumblr");
+
+    fake.revoke_access();
+    assert!(run(client.verify_credentials()).unwrap_err().is_unauthorized());
+}
+
+#[test]
+fn retries() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = AsyncTumblrClient::from(fake.client().retry_policy(fast_retries(3)));
+
+    fake.fail("user/info", Failure::Status(500), 1);
+    fake.fail("user/info", Failure::TooManyRequests(None), 1);
+    run(client.verify_credentials()).unwrap();
+    assert_eq!(fake.requests().len(), 3);
+
+    fake.fail("user/info", Failure::Status(503), 3);
+    let e = run(client.verify_credentials()).unwrap_err();
+    assert!(e.to_string().contains("503"), "{}", e);
+}
+
+#[test]
+fn writes_are_not_resent() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = AsyncTumblrClient::from(fake.client().retry_policy(fast_retries(3)).write_policy(WritePolicy::Unambiguous));
+
+    fake.fail("rumblr/posts", Failure::AfterProcessing(500), 1);
+    assert!(run(client.create_npf_post("rumblr", &[ContentBlock::Text(TextBlock::new("Once"))], None)).is_err());
+    assert_eq!(fake.requests().len(), 1);
+    assert_eq!(fake.state().blog("rumblr").unwrap().posts.len(), 1);
+
+    // unless Tumblr provably didn't process them
+    fake.fail("rumblr/posts", Failure::TooManyRequests(None), 1);
+    run(client.create_npf_post("rumblr", &[ContentBlock::Text(TextBlock::new("Twice"))], None)).unwrap();
+    assert_eq!(fake.requests().len(), 3);
+    assert_eq!(fake.state().blog("rumblr").unwrap().posts.len(), 2);
+}
+
+#[test]
+fn rate_limited() {
+    let fake = FakeTumblr::start().unwrap();
+    let client = AsyncTumblrClient::from(fake.client()
+        .retry_policy(fast_retries(3).deadline(Duration::from_secs(1)))
+        .throttle(Throttle::new(1, Duration::from_secs(3600))));
+
+    run(client.verify_credentials()).unwrap();
+    match run(client.verify_credentials()) {
+        Err(TumblrClientError::RateLimited { wait }) => assert!(wait > Duration::from_secs(3500), "{:?}", wait),
+        result => panic!("not rate limited: {:?}", result.map(|user| user.name)),
+    }
+    assert_eq!(fake.requests().len(), 1);
+}
+
+#[test]
+fn oauth2_refresh() {
+    let fake = FakeTumblr::start().unwrap();
+    let refreshed = Arc::new(Mutex::new(0));
+    let client = {
+        let refreshed = refreshed.clone();
+        fake.consumer_client().on_token_refresh(move |_| *refreshed.lock().unwrap() += 1)
+    };
+
+    let url = client.oauth2_authorize_url(&[Scope::Basic, Scope::OfflineAccess], "xyz", None).unwrap();
+    let code = reqwest::get(&url).unwrap().text().unwrap();
+    let client = AsyncTumblrClient::from(client.oauth2_exchange_code(&code, None).unwrap());
+    let token = client.client().get_oauth2_token().unwrap();
+    assert_eq!(run(client.verify_credentials()).unwrap().name, "rumblr");
+
+    fake.expire_oauth2_tokens();
+    assert_eq!(run(client.verify_credentials()).unwrap().name, "rumblr");
+    assert_eq!(*refreshed.lock().unwrap(), 1);
+    assert_ne!(client.client().get_oauth2_token().unwrap().access_token, token.access_token);
+
+    // the refreshed token is shared with the pages of a stream
+    fake.expire_oauth2_tokens();
+    let likes = Runtime::new().unwrap().block_on(client.user_likes_stream(None).collect()).unwrap();
+    assert!(likes.is_empty());
+    assert_eq!(*refreshed.lock().unwrap(), 2);
+}